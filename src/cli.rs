@@ -0,0 +1,597 @@
+use crate::*;
+use limits::{Limits, RULE_VARIABLES_CAP};
+use normal::NormalForm;
+use parser::Grammar;
+
+use anyhow::{anyhow, Context, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Solve(PathBuf),
+    Check(PathBuf),
+    Table(String),
+    Explain(PathBuf, String),
+    Repl(Option<PathBuf>),
+    Fmt(PathBuf),
+    Normalize(String),
+    Help,
+    Version,
+}
+
+// Format in which the results of the queries are printed.
+// `Text` prints a sentence per query, `Compact` prints all results on a single line and
+// `Json` prints the rules, facts and results as a single JSON document, see report::Report.
+// The table command prints `Markdown` by default and also supports `Csv` and `Text`, see
+// table::TableFormat.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+    #[default]
+    Text,
+    Compact,
+    Json,
+    Markdown,
+    Csv,
+}
+
+// Inference engine used to answer the queries. `Backward` resolves each query on its own,
+// `Forward` derives every identifier from the facts and also reports what was derived and
+// `Sat` decides each query with a SAT solver, which scales to large rule sets.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum Mode {
+    #[default]
+    Backward,
+    Forward,
+    Sat,
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Verbosity {
+    Quiet,
+    #[default]
+    Normal,
+    Verbose,
+}
+
+// Parsed command line arguments, a path of `-` refers to the standard input.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Cli {
+    pub command: Command,
+    pub format: Format,
+    pub verbosity: Verbosity,
+    pub explain: bool,
+    pub grammar: Grammar,
+    pub mode: Mode,
+    // Only check that the file is formatted instead of printing it formatted
+    pub check: bool,
+    // Fail when the input has any warnings, see lint::lint
+    pub deny_warnings: bool,
+    // Only print the rows of a truth table in which its rule holds
+    pub satisfying: bool,
+    // Normal form to convert a rule to, all of them when None
+    pub form: Option<NormalForm>,
+    // Minimise the clauses and terms of normal forms, see normal::normal_form
+    pub minimize: bool,
+    pub limits: Limits,
+}
+
+impl Cli {
+    // Parses the arguments, excluding the program name. When the first positional
+    // argument is not a command it is used as the file to solve.
+    pub fn parse<I, T>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        let mut format = None;
+        let mut verbosity = Verbosity::default();
+        let mut explain = false;
+        let mut grammar = Grammar::default();
+        let mut mode = Mode::default();
+        let mut check = false;
+        let mut deny_warnings = false;
+        let mut satisfying = false;
+        let mut form = None;
+        let mut minimize = false;
+        let mut limits = Limits::default();
+        // Whether --max-depth was given, its value alone does not tell
+        let mut max_depth = false;
+        let mut positional: Vec<String> = vec![];
+        let mut args = args.into_iter().map(Into::into);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Cli::new(Command::Help)),
+                "-V" | "--version" => return Ok(Cli::new(Command::Version)),
+                "-v" | "--verbose" => verbosity = Verbosity::Verbose,
+                "-q" | "--quiet" => verbosity = Verbosity::Quiet,
+                "-e" | "--explain" => explain = true,
+                "--left-to-right" => grammar = Grammar::LeftToRight,
+                "--check" => check = true,
+                "--deny-warnings" => deny_warnings = true,
+                "--satisfying" => satisfying = true,
+                "--minimize" => minimize = true,
+                "--form" => form = Some(parse_form(&args.next().context("Missing form")?)?),
+                a if a.starts_with("--form=") => form = Some(parse_form(&a["--form=".len()..])?),
+                "-f" | "--format" => {
+                    format = Some(parse_format(&args.next().context("Missing format")?)?);
+                }
+                a if a.starts_with("--format=") => {
+                    format = Some(parse_format(&a["--format=".len()..])?);
+                }
+                "-m" | "--mode" => mode = parse_mode(&args.next().context("Missing mode")?)?,
+                a if a.starts_with("--mode=") => mode = parse_mode(&a["--mode=".len()..])?,
+                "--max-variables" | "--max-rows" | "--max-depth" | "--timeout" => {
+                    let value = args
+                        .next()
+                        .with_context(|| format!("Missing {}", &arg[2..]))?;
+                    parse_limit(&mut limits, &arg, &value)?;
+                    max_depth |= arg == "--max-depth";
+                }
+                a if a.starts_with("--max-") || a.starts_with("--timeout=") => {
+                    let (name, value) = a
+                        .split_once('=')
+                        .ok_or_else(|| anyhow!("Unknown option: '{}'", a))?;
+                    parse_limit(&mut limits, name, value)?;
+                    max_depth |= name == "--max-depth";
+                }
+                a if a.starts_with('-') && a != "-" => {
+                    return Err(anyhow!("Unknown option: '{}'", a));
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let command = match positional.next().as_deref() {
+            Some("solve") => Command::Solve(positional.next().context("Missing file")?.into()),
+            Some("check") => Command::Check(positional.next().context("Missing file")?.into()),
+            Some("table") => Command::Table(positional.next().context("Missing rule")?),
+            Some("explain") => {
+                let file = positional.next().context("Missing file")?;
+                let query = positional.next().context("Missing query")?;
+                if !is_identifier(&query) {
+                    return Err(anyhow!("Invalid query: '{}'", query));
+                }
+                Command::Explain(file.into(), query)
+            }
+            Some("repl") => Command::Repl(positional.next().map(PathBuf::from)),
+            Some("fmt") => Command::Fmt(positional.next().context("Missing file")?.into()),
+            Some("normalize") => Command::Normalize(positional.next().context("Missing rule")?),
+            Some(file) => Command::Solve(file.into()),
+            None => return Err(anyhow!("Missing command")),
+        };
+        if let Some(arg) = positional.next() {
+            return Err(anyhow!("Unexpected argument: '{}'", arg));
+        }
+        let explains = explain || matches!(command, Command::Explain(..));
+        if explains && mode != Mode::Backward {
+            return Err(anyhow!("Explanations are only available in backward mode"));
+        }
+        if check && !matches!(command, Command::Fmt(_)) {
+            return Err(anyhow!("--check is only available for fmt"));
+        }
+        if (form.is_some() || minimize) && !matches!(command, Command::Normalize(_)) {
+            return Err(anyhow!(
+                "--form and --minimize are only available for normalize"
+            ));
+        }
+        let table = matches!(command, Command::Table(_));
+        if satisfying && !table {
+            return Err(anyhow!("--satisfying is only available for table"));
+        }
+        let format = match (table, format) {
+            (true, None) => Format::Markdown,
+            (true, Some(Format::Compact | Format::Json)) => {
+                return Err(anyhow!(
+                    "Tables can only be printed as text, markdown or csv"
+                ));
+            }
+            (false, Some(Format::Markdown | Format::Csv)) => {
+                return Err(anyhow!(
+                    "Formats markdown and csv are only available for table"
+                ));
+            }
+            (_, format) => format.unwrap_or_default(),
+        };
+        if max_depth && mode != Mode::Backward {
+            return Err(anyhow!("--max-depth is only available in backward mode"));
+        }
+        Ok(Cli {
+            command,
+            format,
+            verbosity,
+            explain,
+            grammar,
+            mode,
+            check,
+            deny_warnings,
+            satisfying,
+            form,
+            minimize,
+            limits,
+        })
+    }
+
+    fn new(command: Command) -> Self {
+        Cli {
+            command,
+            format: Format::default(),
+            verbosity: Verbosity::default(),
+            explain: false,
+            grammar: Grammar::default(),
+            mode: Mode::default(),
+            check: false,
+            deny_warnings: false,
+            satisfying: false,
+            form: None,
+            minimize: false,
+            limits: Limits::default(),
+        }
+    }
+}
+
+fn parse_format(format: &str) -> Result<Format> {
+    match format {
+        "text" => Ok(Format::Text),
+        "compact" => Ok(Format::Compact),
+        "json" => Ok(Format::Json),
+        "markdown" => Ok(Format::Markdown),
+        "csv" => Ok(Format::Csv),
+        _ => Err(anyhow!("Unknown format: '{}'", format)),
+    }
+}
+
+fn parse_mode(mode: &str) -> Result<Mode> {
+    match mode {
+        "backward" => Ok(Mode::Backward),
+        "forward" => Ok(Mode::Forward),
+        "sat" => Ok(Mode::Sat),
+        _ => Err(anyhow!("Unknown mode: '{}'", mode)),
+    }
+}
+
+fn parse_form(form: &str) -> Result<NormalForm> {
+    match form {
+        "nnf" => Ok(NormalForm::Nnf),
+        "cnf" => Ok(NormalForm::Cnf),
+        "dnf" => Ok(NormalForm::Dnf),
+        _ => Err(anyhow!("Unknown form: '{}'", form)),
+    }
+}
+
+// Sets the limit of the option `name` to `value`, a number of milliseconds for --timeout
+fn parse_limit(limits: &mut Limits, name: &str, value: &str) -> Result<()> {
+    let number: usize = value
+        .parse()
+        .map_err(|_| anyhow!("Invalid value for {}: '{}'", name, value))?;
+    match name {
+        "--max-variables" if number > RULE_VARIABLES_CAP => {
+            return Err(anyhow!(
+                "Invalid value for {}: '{}', at most {} is supported",
+                name,
+                value,
+                RULE_VARIABLES_CAP
+            ))
+        }
+        "--max-variables" => limits.rule_variables = number,
+        "--max-rows" => limits.table_rows = number,
+        "--max-depth" => limits.depth = number,
+        "--timeout" => limits.timeout = Some(Duration::from_millis(number as u64)),
+        _ => return Err(anyhow!("Unknown option: '{}'", name)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_cli {
+    use super::*;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    fn parse(args: &[&str]) -> Result<Cli> {
+        Cli::parse(args.iter().copied())
+    }
+
+    #[test]
+    fn file() -> Result<()> {
+        assert_eq!(
+            parse(&["input.txt"])?,
+            Cli::new(Command::Solve("input.txt".into()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn commands() -> Result<()> {
+        assert_eq!(
+            parse(&["solve", "a.txt"])?.command,
+            Command::Solve("a.txt".into())
+        );
+        assert_eq!(
+            parse(&["check", "a.txt"])?.command,
+            Command::Check("a.txt".into())
+        );
+        assert_eq!(
+            parse(&["table", "A => B"])?.command,
+            Command::Table("A => B".into())
+        );
+        assert_eq!(
+            parse(&["explain", "a.txt", "A"])?.command,
+            Command::Explain("a.txt".into(), "A".into())
+        );
+        assert_eq!(
+            parse(&["explain", "a.txt", "HasFever"])?.command,
+            Command::Explain("a.txt".into(), "HasFever".into())
+        );
+        assert_eq!(parse(&["repl"])?.command, Command::Repl(None));
+        assert_eq!(
+            parse(&["repl", "a.txt"])?.command,
+            Command::Repl(Some("a.txt".into()))
+        );
+        assert_eq!(
+            parse(&["fmt", "a.txt"])?.command,
+            Command::Fmt("a.txt".into())
+        );
+        assert_eq!(
+            parse(&["normalize", "A => B"])?.command,
+            Command::Normalize("A => B".into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stdin() -> Result<()> {
+        assert_eq!(parse(&["-"])?.command, Command::Solve("-".into()));
+        assert_eq!(parse(&["check", "-"])?.command, Command::Check("-".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn help_and_version() -> Result<()> {
+        assert_eq!(parse(&["--help"])?.command, Command::Help);
+        assert_eq!(parse(&["solve", "-h"])?.command, Command::Help);
+        assert_eq!(parse(&["--version"])?.command, Command::Version);
+        assert_eq!(parse(&["-V", "foo", "bar"])?.command, Command::Version);
+        Ok(())
+    }
+
+    #[test]
+    fn options() -> Result<()> {
+        let result = parse(&["-v", "solve", "a.txt", "--format", "compact"])?;
+        assert_eq!(result.verbosity, Verbosity::Verbose);
+        assert_eq!(result.format, Format::Compact);
+
+        let result = parse(&["a.txt", "--explain", "--format=json"])?;
+        assert_eq!(result.format, Format::Json);
+
+        let result = parse(&["a.txt", "--quiet", "--format=text"])?;
+        assert_eq!(result.verbosity, Verbosity::Quiet);
+        assert_eq!(result.format, Format::Text);
+        assert!(!result.explain);
+
+        assert!(parse(&["--explain", "a.txt"])?.explain);
+        assert!(parse(&["a.txt", "-e"])?.explain);
+
+        assert_eq!(parse(&["a.txt"])?.grammar, Grammar::Precedence);
+        assert_eq!(
+            parse(&["--left-to-right", "a.txt"])?.grammar,
+            Grammar::LeftToRight
+        );
+
+        assert_eq!(parse(&["a.txt"])?.mode, Mode::Backward);
+        assert_eq!(parse(&["--mode", "forward", "a.txt"])?.mode, Mode::Forward);
+        assert_eq!(
+            parse(&["check", "a.txt", "-m", "backward"])?.mode,
+            Mode::Backward
+        );
+        assert_eq!(parse(&["--mode=forward", "a.txt"])?.mode, Mode::Forward);
+        assert_eq!(parse(&["--mode=sat", "a.txt"])?.mode, Mode::Sat);
+
+        assert!(!parse(&["fmt", "a.txt"])?.check);
+        assert!(parse(&["fmt", "--check", "a.txt"])?.check);
+        assert!(parse(&["check", "--deny-warnings", "a.txt"])?.deny_warnings);
+        Ok(())
+    }
+
+    #[test]
+    fn table() -> Result<()> {
+        let result = parse(&["table", "A => B"])?;
+        assert_eq!(result.format, Format::Markdown);
+        assert!(!result.satisfying);
+
+        let result = parse(&["table", "A => B", "--format=csv", "--satisfying"])?;
+        assert_eq!(result.format, Format::Csv);
+        assert!(result.satisfying);
+        assert_eq!(
+            parse(&["-f", "text", "table", "A => B"])?.format,
+            Format::Text
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn error_table_format() {
+        let result = parse(&["table", "A => B", "-f", "json"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Tables can only be printed as text, markdown or csv"
+        );
+        let result = parse(&["a.txt", "-f", "csv"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Formats markdown and csv are only available for table"
+        );
+        let result = parse(&["a.txt", "--satisfying"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "--satisfying is only available for table"
+        );
+    }
+
+    #[test]
+    fn normalize() -> Result<()> {
+        let result = parse(&["normalize", "A => B"])?;
+        assert_eq!((result.form, result.minimize), (None, false));
+        let result = parse(&["normalize", "--form", "cnf", "--minimize", "A => B"])?;
+        assert_eq!(
+            (result.form, result.minimize),
+            (Some(NormalForm::Cnf), true)
+        );
+        assert_eq!(
+            parse(&["normalize", "--form=nnf", "A => B"])?.form,
+            Some(NormalForm::Nnf)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn error_normalize() {
+        let result = parse(&["normalize", "--form=xnf", "A => B"]);
+        assert_eq!(result.unwrap_err().to_string(), "Unknown form: 'xnf'");
+        let result = parse(&["table", "--minimize", "A => B"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "--form and --minimize are only available for normalize"
+        );
+    }
+
+    #[test]
+    fn limits() -> Result<()> {
+        assert_eq!(parse(&["a.txt"])?.limits, Limits::default());
+        let result = parse(&[
+            "--max-variables",
+            "8",
+            "--max-rows=1000",
+            "--max-depth",
+            "50",
+            "--timeout=200",
+            "a.txt",
+        ])?;
+        assert_eq!(
+            result.limits,
+            Limits {
+                rule_variables: 8,
+                table_rows: 1000,
+                depth: 50,
+                timeout: Some(Duration::from_millis(200)),
+            }
+        );
+        assert_eq!(
+            parse(&["--mode=sat", "--max-rows", "10", "a.txt"])?
+                .limits
+                .table_rows,
+            10
+        );
+        assert_eq!(
+            parse(&["--mode=forward", "--timeout=10", "a.txt"])?
+                .limits
+                .timeout,
+            Some(Duration::from_millis(10))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn error_limits() {
+        let result = parse(&["--max-depth", "deep", "a.txt"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid value for --max-depth: 'deep'"
+        );
+        let result = parse(&["a.txt", "--timeout"]);
+        assert_eq!(result.unwrap_err().to_string(), "Missing timeout");
+        let result = parse(&["--max-size=3", "a.txt"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Unknown option: '--max-size'"
+        );
+        let result = parse(&["--max-variables=63", "--max-rows=1000000000000", "a.txt"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid value for --max-variables: '63', at most 30 is supported"
+        );
+        let result = parse(&["--mode=forward", "--max-depth=10", "a.txt"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "--max-depth is only available in backward mode"
+        );
+        // Also when it is given with its default value
+        let result = parse(&["--mode=sat", "--max-depth", "1000", "a.txt"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "--max-depth is only available in backward mode"
+        );
+    }
+
+    #[test]
+    fn error_no_arguments() {
+        let result = parse(&[]);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Missing command");
+    }
+
+    #[test]
+    fn error_too_many_arguments() {
+        let result = parse(&["foo", "bar"]);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Unexpected argument: 'bar'"
+        );
+    }
+
+    #[test]
+    fn error_missing_argument() {
+        let result = parse(&["explain", "a.txt"]);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Missing query");
+    }
+
+    #[test]
+    fn error_invalid_query() {
+        let result = parse(&["explain", "a.txt", "A+B"]);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Invalid query: 'A+B'");
+    }
+
+    #[test]
+    fn error_unknown_option() {
+        let result = parse(&["--foo", "a.txt"]);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Unknown option: '--foo'");
+    }
+
+    #[test]
+    fn error_unknown_format() {
+        let result = parse(&["--format", "xml", "a.txt"]);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "Unknown format: 'xml'");
+    }
+
+    #[test]
+    fn error_unknown_mode() {
+        let result = parse(&["--mode", "sideways", "a.txt"]);
+        assert_eq!(result.unwrap_err().to_string(), "Unknown mode: 'sideways'");
+    }
+
+    #[test]
+    fn error_forward_explain() {
+        let result = parse(&["--mode", "forward", "-e", "a.txt"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Explanations are only available in backward mode"
+        );
+        assert!(parse(&["--mode", "forward", "explain", "a.txt", "A"]).is_err());
+    }
+
+    #[test]
+    fn error_check_without_fmt() {
+        let result = parse(&["--check", "a.txt"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "--check is only available for fmt"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+use std::ops::Range;
+
+// Position of an error in an input. Errors with a location are rendered with the line they
+// occurred on and a caret under the characters that caused it:
+//
+// Unexpected character: $
+//  --> rules.txt:3:5
+//   |
+// 3 | A + $ => C
+//   |     ^
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    // Line number counted from 1
+    pub line: usize,
+    // Text of the line as it appears in the input
+    pub source: String,
+    // Characters of `source` the error points at, counted from 0
+    pub columns: Range<usize>,
+}
+
+impl Location {
+    pub fn new(
+        file: impl Into<String>,
+        line: usize,
+        source: impl Into<String>,
+        columns: Range<usize>,
+    ) -> Self {
+        Location {
+            file: file.into(),
+            line,
+            source: source.into(),
+            columns,
+        }
+    }
+
+    // Writes everything below the message of the error
+    pub fn fmt_snippet(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let gutter = " ".repeat(self.line.to_string().len());
+        let source = self.source.trim_end();
+        // Tabs are kept in front of the caret so it lines up with the source
+        let indent: String = source
+            .chars()
+            .chain(std::iter::repeat(' '))
+            .take(self.columns.start)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = self.columns.len().max(1);
+        writeln!(
+            f,
+            "{}--> {}:{}:{}",
+            gutter,
+            self.file,
+            self.line,
+            self.columns.start + 1
+        )?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", self.line, source)?;
+        writeln!(f, "{} | {}{}", gutter, indent, "^".repeat(width))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_snippet(f)
+    }
+}
+
+#[cfg(test)]
+mod tests_diagnostics {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn display() {
+        let result = Location::new("rules.txt", 3, "A + $ => C", 4..5);
+        assert_eq!(
+            result.to_string(),
+            indoc! {"
+                 --> rules.txt:3:5
+                  |
+                3 | A + $ => C
+                  |     ^
+            "}
+        );
+    }
+
+    #[test]
+    fn wide_gutter_and_tabs() {
+        let result = Location::new("<input>", 12, "\tA + B", 6..7);
+        assert_eq!(
+            result.to_string(),
+            "  --> <input>:12:7\n   |\n12 | \tA + B\n   | \t     ^\n"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use crate::*;
+use diagnostics::Location;
+use parser::{Span, Token};
+use resolver::Contradiction;
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+// Error returned by the library. Errors in the syntax of a rule keep the rule and the span
+// of the offending characters in it, errors that are found while reading an input also
+// know their location in that input.
+#[derive(Debug)]
+pub enum ExpertSystemError {
+    Lex {
+        error: LexError,
+        rule: String,
+        span: Span,
+        location: Option<Box<Location>>,
+    },
+    Parse {
+        error: ParseError,
+        rule: String,
+        span: Span,
+        location: Option<Box<Location>>,
+    },
+    Input {
+        error: InputError,
+        location: Option<Box<Location>>,
+    },
+    Io {
+        path: PathBuf,
+        source: io::Error,
+        location: Option<Box<Location>>,
+    },
+    Contradiction(Contradiction),
+    LimitExceeded {
+        limit: Limit,
+        max: usize,
+        actual: usize,
+    },
+}
+
+// A rule contains characters that do not form a token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    UnexpectedCharacter(char),
+    IncompleteImplicator,
+}
+
+// The tokens of a rule do not form a valid rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    UnexpectedEnd,
+    MissingImplicator,
+    MissingClosingParenthesis,
+    UnexpectedToken(Token),
+    InvalidFactor(Token),
+    InvalidOperator(Token),
+    UnknownIdentifier(String),
+    UnassignedIdentifier(String),
+}
+
+// The lines of an input do not form a valid input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputError {
+    NoFacts,
+    NoQueries,
+    MissingQueries,
+    InvalidFact(String),
+    InvalidQuery(String),
+    AmbiguousIdentifiers(String),
+    InvalidInclude,
+    IncludeCycle(PathBuf),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Limit {
+    // Number of identifiers in a single rule
+    RuleVariables,
+    // Number of rows of the truth tables of all rules
+    TableRows,
+    // Number of identifiers being resolved at the same time
+    Depth,
+    // Milliseconds spent resolving
+    Timeout,
+    // Number of identifiers in a rule whose normal forms are minimised
+    MinimizeVariables,
+}
+
+impl ExpertSystemError {
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            ExpertSystemError::Lex { location, .. }
+            | ExpertSystemError::Parse { location, .. }
+            | ExpertSystemError::Input { location, .. }
+            | ExpertSystemError::Io { location, .. } => location.as_deref(),
+            _ => None,
+        }
+    }
+
+    // Sets the location of a syntax error from the span of the error in its rule, other
+    // errors are returned unchanged.
+    pub fn locate(mut self, locate: impl FnOnce(Span) -> Location) -> Self {
+        match &mut self {
+            ExpertSystemError::Lex { span, location, .. }
+            | ExpertSystemError::Parse { span, location, .. } => {
+                *location = Some(Box::new(locate(*span)))
+            }
+            _ => {}
+        }
+        self
+    }
+
+    fn fmt_message(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpertSystemError::Lex { error, .. } => write!(f, "{}", error),
+            ExpertSystemError::Parse { error, .. } => write!(f, "{}", error),
+            ExpertSystemError::Input { error, .. } => write!(f, "{}", error),
+            ExpertSystemError::Io { path, .. } => write!(f, "Failed to read '{}'", path.display()),
+            ExpertSystemError::Contradiction(contradiction) => write!(f, "{}", contradiction),
+            ExpertSystemError::LimitExceeded { limit, max, actual } => match limit {
+                Limit::RuleVariables => write!(
+                    f,
+                    "Rule has {} identifiers, at most {} are supported",
+                    actual, max
+                ),
+                Limit::TableRows => write!(
+                    f,
+                    "Truth tables need {} rows, at most {} are supported",
+                    actual, max
+                ),
+                Limit::Depth => write!(
+                    f,
+                    "Resolving needs {} nested identifiers, at most {} are supported",
+                    actual, max
+                ),
+                Limit::Timeout => write!(
+                    f,
+                    "Resolving timed out after {} ms, the limit is {} ms",
+                    actual, max
+                ),
+                Limit::MinimizeVariables => write!(
+                    f,
+                    "Rule has {} identifiers, at most {} can be minimised",
+                    actual, max
+                ),
+            },
+        }
+    }
+}
+
+// Errors with a location are rendered with the line they occurred on, see Location
+impl fmt::Display for ExpertSystemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location() {
+            Some(location) => {
+                self.fmt_message(f)?;
+                writeln!(f)?;
+                location.fmt_snippet(f)
+            }
+            None => self.fmt_message(f),
+        }
+    }
+}
+
+impl std::error::Error for ExpertSystemError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExpertSystemError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<Contradiction> for ExpertSystemError {
+    fn from(contradiction: Contradiction) -> Self {
+        ExpertSystemError::Contradiction(contradiction)
+    }
+}
+
+impl From<InputError> for ExpertSystemError {
+    fn from(error: InputError) -> Self {
+        ExpertSystemError::Input {
+            error,
+            location: None,
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexError::UnexpectedCharacter(c) => write!(f, "Unexpected character: {}", c),
+            LexError::IncompleteImplicator => write!(f, "Unable to finish lexing implicator"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedEnd => write!(f, "Unexpected end of token list"),
+            ParseError::MissingImplicator => write!(f, "No implicator found"),
+            ParseError::MissingClosingParenthesis => write!(f, "Missing closing parenthesis"),
+            ParseError::UnexpectedToken(t) => write!(f, "Unexpected token '{:?}'", t),
+            ParseError::InvalidFactor(t) => write!(f, "Invalid factor token '{:?}'", t),
+            ParseError::InvalidOperator(t) => write!(f, "Invalid operator token '{:?}'", t),
+            ParseError::UnknownIdentifier(ident) => write!(f, "Unknown identifier '{}'", ident),
+            ParseError::UnassignedIdentifier(ident) => {
+                write!(f, "Unassigned identifier '{}'", ident)
+            }
+        }
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::NoFacts => write!(f, "No facts in input file"),
+            InputError::NoQueries => write!(f, "No queries in input file"),
+            InputError::MissingQueries => write!(f, "No queries for these facts"),
+            InputError::InvalidFact(ident) => write!(f, "Invalid identifier in facts: '{}'", ident),
+            InputError::InvalidQuery(ident) => {
+                write!(f, "Invalid identifier in query: '{}'", ident)
+            }
+            InputError::AmbiguousIdentifiers(group) => write!(
+                f,
+                "Ambiguous identifiers '{}', separate identifiers with commas",
+                group
+            ),
+            InputError::InvalidInclude => write!(f, "Expected a quoted path after #include"),
+            InputError::IncludeCycle(path) => {
+                write!(
+                    f,
+                    "Include cycle, '{}' is already being read",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_expert_system_error {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use std::error::Error;
+
+    #[test]
+    fn display() {
+        let result = ExpertSystemError::Parse {
+            error: ParseError::MissingImplicator,
+            rule: "A+B".to_string(),
+            span: Span::new(3, 4),
+            location: None,
+        };
+        assert_eq!(result.to_string(), "No implicator found");
+
+        let result = ExpertSystemError::LimitExceeded {
+            limit: Limit::RuleVariables,
+            max: 24,
+            actual: 30,
+        };
+        assert_eq!(
+            result.to_string(),
+            "Rule has 30 identifiers, at most 24 are supported"
+        );
+
+        let result = ExpertSystemError::LimitExceeded {
+            limit: Limit::Depth,
+            max: 2,
+            actual: 3,
+        };
+        assert_eq!(
+            result.to_string(),
+            "Resolving needs 3 nested identifiers, at most 2 are supported"
+        );
+    }
+
+    #[test]
+    fn display_location() {
+        let result = ExpertSystemError::from(InputError::NoFacts).locate(|_| unreachable!());
+        assert_eq!(result.to_string(), "No facts in input file");
+
+        let result = ExpertSystemError::Lex {
+            error: LexError::UnexpectedCharacter('$'),
+            rule: "A+$=>C".to_string(),
+            span: Span::new(2, 3),
+            location: None,
+        }
+        .locate(|span| {
+            Location::new(
+                "rules.txt",
+                3,
+                "A + $ => C",
+                2 * span.start..2 * span.end - 1,
+            )
+        });
+        assert_eq!(
+            result.to_string(),
+            indoc! {"
+                Unexpected character: $
+                 --> rules.txt:3:5
+                  |
+                3 | A + $ => C
+                  |     ^
+            "}
+        );
+    }
+
+    #[test]
+    fn io_source() {
+        let result = ExpertSystemError::Io {
+            path: PathBuf::from("rules.txt"),
+            source: io::Error::new(io::ErrorKind::NotFound, "not found"),
+            location: None,
+        };
+        assert_eq!(result.to_string(), "Failed to read 'rules.txt'");
+        assert_eq!(result.source().unwrap().to_string(), "not found");
+    }
+}
@@ -0,0 +1,113 @@
+use crate::*;
+use parser::Truth;
+
+use std::fmt;
+
+// Explanation of the value of an identifier, as found by the Resolver.
+// It forms a tree in which a rule is explained by the values of its other identifiers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub ident: String,
+    pub value: Truth,
+    pub reason: Reason,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    // The identifier is one of the initial facts
+    Fact,
+    // The identifier is decided by `rule`, given the values of the other identifiers
+    Rule {
+        rule: String,
+        premises: Vec<Explanation>,
+    },
+    // No rule decides the identifier, so it is false
+    Default,
+    // The identifier is already being explained higher up in the tree
+    Cycle,
+}
+
+impl Explanation {
+    fn fmt_indented(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        write!(f, "{}{} is {}", "  ".repeat(depth), self.ident, self.value)?;
+        match &self.reason {
+            Reason::Fact => writeln!(f, " because it is a fact"),
+            Reason::Rule { rule, premises } => {
+                writeln!(f, " because {}", rule)?;
+                for premise in premises.iter() {
+                    premise.fmt_indented(f, depth + 1)?;
+                }
+                Ok(())
+            }
+            Reason::Default => writeln!(f, " because no rule determines it"),
+            Reason::Cycle => writeln!(f, ", see above"),
+        }
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests_explanation {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn leaf(ident: &str, value: Truth, reason: Reason) -> Explanation {
+        Explanation {
+            ident: ident.to_string(),
+            value,
+            reason,
+        }
+    }
+
+    #[test]
+    fn fact() {
+        let result = leaf("A", Truth::True, Reason::Fact);
+        assert_eq!(result.to_string(), "A is true because it is a fact\n");
+    }
+
+    #[test]
+    fn default() {
+        let result = leaf("A", Truth::False, Reason::Default);
+        assert_eq!(
+            result.to_string(),
+            "A is false because no rule determines it\n"
+        );
+    }
+
+    #[test]
+    fn nested() {
+        let result = Explanation {
+            ident: "C".to_string(),
+            value: Truth::True,
+            reason: Reason::Rule {
+                rule: "A + B => C".to_string(),
+                premises: vec![
+                    leaf("A", Truth::True, Reason::Fact),
+                    Explanation {
+                        ident: "B".to_string(),
+                        value: Truth::True,
+                        reason: Reason::Rule {
+                            rule: "C <=> B".to_string(),
+                            premises: vec![leaf("C", Truth::True, Reason::Cycle)],
+                        },
+                    },
+                ],
+            },
+        };
+        assert_eq!(
+            result.to_string(),
+            indoc! {"
+                C is true because A + B => C
+                  A is true because it is a fact
+                  B is true because C <=> B
+                    C is true, see above
+            "}
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeSet;
+
+// Expr is the parsed form of a rule. Identifiers are referred to by their index in the
+// sorted list of variables of the rule, so an expression can be evaluated for a row of a
+// TruthTable without looking up any names. Variable `i` of `len` variables takes its value
+// from bit `len - 1 - i` of the row.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expr {
+    Bool(bool),
+    Var(usize),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Xor(Box<Expr>, Box<Expr>),
+    Imply(Box<Expr>, Box<Expr>),
+    Iff(Box<Expr>, Box<Expr>),
+}
+
+// An empty rule holds for every assignment
+impl Default for Expr {
+    fn default() -> Self {
+        Expr::Bool(true)
+    }
+}
+
+impl Expr {
+    pub fn evaluate(&self, row: usize, len: usize) -> bool {
+        match self {
+            Expr::Bool(b) => *b,
+            Expr::Var(i) => row & (1 << (len - 1 - i)) != 0,
+            Expr::Not(e) => !e.evaluate(row, len),
+            Expr::And(l, r) => l.evaluate(row, len) && r.evaluate(row, len),
+            Expr::Or(l, r) => l.evaluate(row, len) || r.evaluate(row, len),
+            Expr::Xor(l, r) => l.evaluate(row, len) ^ r.evaluate(row, len),
+            Expr::Imply(l, r) => !l.evaluate(row, len) || r.evaluate(row, len),
+            Expr::Iff(l, r) => l.evaluate(row, len) == r.evaluate(row, len),
+        }
+    }
+
+    // Returns the indices of all variables used in the expression, in ascending order
+    pub fn variables(&self) -> Vec<usize> {
+        let mut result = BTreeSet::new();
+        self.collect_variables(&mut result);
+        result.into_iter().collect()
+    }
+
+    fn collect_variables(&self, result: &mut BTreeSet<usize>) {
+        match self {
+            Expr::Bool(_) => {}
+            Expr::Var(i) => {
+                result.insert(*i);
+            }
+            Expr::Not(e) => e.collect_variables(result),
+            Expr::And(l, r)
+            | Expr::Or(l, r)
+            | Expr::Xor(l, r)
+            | Expr::Imply(l, r)
+            | Expr::Iff(l, r) => {
+                l.collect_variables(result);
+                r.collect_variables(result);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_expr {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn var(i: usize) -> Box<Expr> {
+        Box::new(Expr::Var(i))
+    }
+
+    fn table(expr: &Expr, len: usize) -> Vec<bool> {
+        (0..1 << len).map(|row| expr.evaluate(row, len)).collect()
+    }
+
+    #[test]
+    fn bool() {
+        assert_eq!(table(&Expr::Bool(true), 0), vec![true]);
+        assert_eq!(table(&Expr::Bool(false), 0), vec![false]);
+    }
+
+    #[test]
+    fn var_order() {
+        assert_eq!(table(&Expr::Var(0), 2), vec![false, false, true, true]);
+        assert_eq!(table(&Expr::Var(1), 2), vec![false, true, false, true]);
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(table(&Expr::Not(var(0)), 1), vec![true, false]);
+        assert_eq!(
+            table(&Expr::And(var(0), var(1)), 2),
+            vec![false, false, false, true]
+        );
+        assert_eq!(
+            table(&Expr::Or(var(0), var(1)), 2),
+            vec![false, true, true, true]
+        );
+        assert_eq!(
+            table(&Expr::Xor(var(0), var(1)), 2),
+            vec![false, true, true, false]
+        );
+        assert_eq!(
+            table(&Expr::Imply(var(0), var(1)), 2),
+            vec![true, true, false, true]
+        );
+        assert_eq!(
+            table(&Expr::Iff(var(0), var(1)), 2),
+            vec![true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn variables() {
+        let expr = Expr::Imply(
+            Box::new(Expr::And(var(2), Box::new(Expr::Not(var(0))))),
+            Box::new(Expr::Or(var(2), Box::new(Expr::Bool(true)))),
+        );
+        assert_eq!(expr.variables(), vec![0, 2]);
+        assert_eq!(Expr::Bool(false).variables(), Vec::<usize>::new());
+    }
+}
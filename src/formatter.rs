@@ -0,0 +1,301 @@
+use crate::*;
+use diagnostics::Location;
+use error::{ExpertSystemError, InputError};
+use input::{format_identifiers, original_columns, parse_identifiers};
+use parser::{Direction, RuleParser, Span, Token};
+
+use std::borrow::Borrow;
+
+// Line of an input file in its canonical form, without its trailing comment
+enum Line {
+    Blank,
+    // Comments and directives, which are kept as they are. An indented comment continues
+    // the trailing comments above it and is indented to line up with them.
+    Verbatim(String, bool),
+    Rule(String, Direction, String),
+    Statement(String),
+}
+
+// Formats the lines of an input file. Rules are written with a single space around the
+// binary operators and implicators, facts and queries the way `parse_identifiers` reads
+// them. Within a block of lines without blank lines in between, the implicators and the
+// trailing comments line up, e.g.
+//
+// A + B => C    # both
+// C     => D
+// =AB           # facts
+//
+// Runs of blank lines are reduced to one. Included files are not followed, the `#include`
+// lines are kept as they are.
+pub fn format_lines<T>(lines: &[T], file: &str) -> Result<String, ExpertSystemError>
+where
+    T: Borrow<str>,
+{
+    let mut blocks: Vec<Vec<(Line, Option<String>)>> = vec![vec![]];
+    for (index, original) in lines.iter().enumerate() {
+        let original = original.borrow();
+        let locate = |columns: &[usize], span: Span| -> Location {
+            Location::new(file, index + 1, original, original_columns(columns, span))
+        };
+        match format_line(original, locate)? {
+            (Line::Blank, _) if blocks.last().is_some_and(Vec::is_empty) => {}
+            (Line::Blank, _) => blocks.push(vec![]),
+            line => blocks.last_mut().unwrap().push(line),
+        }
+    }
+
+    let mut result = String::new();
+    for block in blocks.iter().filter(|block| !block.is_empty()) {
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        write_block(&mut result, block);
+    }
+    Ok(result)
+}
+
+// Formats a single rule, e.g. `A+!(B|C)=>D` becomes `A + !(B | C) => D`
+pub fn format_rule(rule: &str) -> Result<String, ExpertSystemError> {
+    let (antecedent, direction, consequent) = split_rule(rule)?;
+    Ok(format!(
+        "{} {} {}",
+        antecedent,
+        implicator(direction),
+        consequent
+    ))
+}
+
+fn write_block(result: &mut String, block: &[(Line, Option<String>)]) {
+    let width = |s: &str| s.chars().count();
+    let antecedent_width = block
+        .iter()
+        .filter_map(|(line, _)| match line {
+            Line::Rule(antecedent, _, _) => Some(width(antecedent)),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+    let contents: Vec<String> = block
+        .iter()
+        .map(|(line, _)| match line {
+            Line::Blank => String::new(),
+            Line::Verbatim(text, _) | Line::Statement(text) => text.clone(),
+            Line::Rule(antecedent, direction, consequent) => format!(
+                "{:<width$} {} {}",
+                antecedent,
+                implicator(*direction),
+                consequent,
+                width = antecedent_width
+            ),
+        })
+        .collect();
+    let comment_column = block
+        .iter()
+        .zip(contents.iter())
+        .filter(|((_, comment), _)| comment.is_some())
+        .map(|(_, content)| width(content) + 2)
+        .max();
+    for ((line, comment), content) in block.iter().zip(contents) {
+        match (line, comment, comment_column) {
+            (Line::Verbatim(_, true), _, Some(column)) => {
+                result.push_str(&format!("{}{}\n", " ".repeat(column), content))
+            }
+            (_, Some(comment), Some(column)) => {
+                result.push_str(&format!("{:<width$}{}\n", content, comment, width = column))
+            }
+            _ => result.push_str(&format!("{}\n", content)),
+        }
+    }
+}
+
+// Splits a line into its canonical form and its trailing comment. `locate` gives the
+// location of a span of the sanitized line, given the columns of its characters.
+fn format_line(
+    original: &str,
+    locate: impl Fn(&[usize], Span) -> Location,
+) -> Result<(Line, Option<String>), ExpertSystemError> {
+    let trimmed = original.trim();
+    if trimmed.is_empty() {
+        return Ok((Line::Blank, None));
+    }
+    if trimmed.starts_with('#') {
+        let indented = original.starts_with(char::is_whitespace);
+        return Ok((Line::Verbatim(trimmed.to_string(), indented), None));
+    }
+    let (code, comment) = match original.split_once('#') {
+        Some((code, comment)) => (code, Some(format!("#{}", comment.trim_end()))),
+        None => (original, None),
+    };
+    let (line, columns) = sanitize::sanitize_line(code);
+    let invalid: fn(String) -> InputError = match line.chars().next() {
+        Some('=') => InputError::InvalidFact,
+        Some('?') => InputError::InvalidQuery,
+        _ => {
+            let (antecedent, direction, consequent) =
+                split_rule(&line).map_err(|err| err.locate(|span| locate(&columns, span)))?;
+            return Ok((Line::Rule(antecedent, direction, consequent), comment));
+        }
+    };
+    let identifiers = parse_identifiers(&line[1..], invalid).map_err(|(error, part)| {
+        let start = line[1..]
+            .find(&part)
+            .map_or(1, |i| line[..1 + i].chars().count());
+        let span = Span::new(start, start + part.chars().count());
+        ExpertSystemError::Input {
+            error,
+            location: Some(Box::new(locate(&columns, span))),
+        }
+    })?;
+    let statement = format!("{}{}", &line[..1], format_identifiers(&identifiers));
+    Ok((Line::Statement(statement), comment))
+}
+
+// Checks that `rule` is valid and returns both sides of its implicator in canonical form
+fn split_rule(rule: &str) -> Result<(String, Direction, String), ExpertSystemError> {
+    RuleParser::new().parse(rule)?;
+    let tokens = RuleParser::new().tokenize(rule)?;
+    let index = tokens
+        .iter()
+        .position(|(token, _)| matches!(token, Token::Implicator(_)))
+        .expect("a parsed rule has an implicator");
+    let direction = match tokens[index].0 {
+        Token::Implicator(direction) => direction,
+        _ => unreachable!(),
+    };
+    Ok((
+        format_tokens(&tokens[..index]),
+        direction,
+        format_tokens(&tokens[index + 1..]),
+    ))
+}
+
+fn format_tokens(tokens: &[(Token, Span)]) -> String {
+    let mut result = String::new();
+    for (token, _) in tokens {
+        match token {
+            Token::Identifier(ident) => result.push_str(ident),
+            Token::Bool(b) => result.push(if *b { '1' } else { '0' }),
+            Token::Operator('!') => result.push('!'),
+            Token::Parenthesis(c) => result.push(*c),
+            Token::Operator(c) => result.push_str(&format!(" {} ", c)),
+            Token::Implicator(direction) => result.push_str(implicator(*direction)),
+        }
+    }
+    result
+}
+
+fn implicator(direction: Direction) -> &'static str {
+    match direction {
+        Direction::UniDirectional => "=>",
+        Direction::BiDirectional => "<=>",
+    }
+}
+
+#[cfg(test)]
+mod tests_formatter {
+    use super::*;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    fn format(lines: &[&str]) -> Result<String> {
+        Ok(format_lines(lines, "<input>")?)
+    }
+
+    #[test]
+    fn rule() -> Result<()> {
+        assert_eq!(format_rule("A+!(B|C)=>D")?, "A + !(B | C) => D");
+        assert_eq!(format_rule("  A  <=>  !B^1 ")?, "A <=> !B ^ 1");
+        assert_eq!(
+            format_rule("HasFever+sensor.3.ok=>flu")?,
+            "HasFever + sensor.3.ok => flu"
+        );
+        assert!(format_rule("A + => B").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn align() -> Result<()> {
+        let result = format(&[
+            "A+B=>C # both",
+            "C=>D",
+            "  =  A B   # facts",
+            "      # more about the facts",
+            "?D",
+        ])?;
+        assert_eq!(
+            result,
+            indoc! {"
+                A + B => C  # both
+                C     => D
+                =AB         # facts
+                            # more about the facts
+                ?D
+            "}
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn blocks() -> Result<()> {
+        let result = format(&[
+            "",
+            "# rules",
+            "LongName => B # first",
+            "",
+            "",
+            "A <=> B  # second",
+            "#include \"more.txt\"",
+            "=ABS,",
+            "?",
+            "",
+        ])?;
+        assert_eq!(
+            result,
+            indoc! {"
+                # rules
+                LongName => B  # first
+
+                A <=> B  # second
+                #include \"more.txt\"
+                =ABS,
+                ?
+            "}
+        );
+        assert_eq!(format(&[])?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn idempotent() -> Result<()> {
+        let lines = [
+            "A | B=>C   # or",
+            "C+D => E",
+            "=A       # facts",
+            "   # continued",
+            "?E",
+        ];
+        let result = format(&lines)?;
+        let lines: Vec<&str> = result.lines().collect();
+        assert_eq!(format(&lines)?, result);
+        Ok(())
+    }
+
+    #[test]
+    fn error_location() {
+        let result = format(&["A => B", "A + $ => C  # comment"]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            indoc! {"
+                Unexpected character: $
+                 --> <input>:2:5
+                  |
+                2 | A + $ => C  # comment
+                  |     ^
+            "}
+        );
+
+        let result = format_lines(&["=A b-c"], "<input>").unwrap_err();
+        assert_eq!(result.location().map(|l| l.columns.clone()), Some(3..6));
+    }
+}
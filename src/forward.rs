@@ -0,0 +1,564 @@
+use crate::*;
+use error::ExpertSystemError;
+use parser::{RuleMap, Truth, TruthTable};
+use resolver::Contradiction;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Instant;
+
+// ForwardChainer derives the value of every identifier in a RuleMap by starting from the
+// facts and repeatedly applying the rules whose sources all have a value. A rule applies
+// to an identifier when every row of its truth table that agrees with the known values
+// gives the identifier the same value.
+//
+// Once no rule applies anymore, identifiers that can not be derived are settled: they are
+// undetermined when a rule would force them true for some values of identifiers that are
+// still open (`A => B | C`) and false otherwise. Identifiers that are left after that
+// depend on each other through a cycle, the first of them is assumed false and the rules
+// are applied again. For rules without cycles this mostly gives the same values as
+// backward chaining with the Resolver, rules are applied one at a time though, so values
+// that only follow from combining several rules can be left undetermined.
+//
+// Only the rules containing an identifier whose value changed are applied again, so facts
+// can be added with `assert_fact` and the next `run` only revisits the rules they affect.
+//
+// A run fails once the timeout of the limits of the map has passed since it started.
+pub struct ForwardChainer<'a> {
+    map: &'a RuleMap,
+    // Every table of the map once, ordered by rule
+    tables: Vec<&'a TruthTable>,
+    // Indices into `tables` of the tables containing each identifier
+    containing: HashMap<&'a str, Vec<usize>>,
+    // Identifiers of the rules and the facts, ordered by identifier
+    identifiers: Vec<String>,
+    // Tables to apply on the next run, since the value of one of their identifiers changed
+    pending: BTreeSet<usize>,
+    facts: HashSet<String>,
+    values: HashMap<String, Truth>,
+    reasons: HashMap<String, &'a TruthTable>,
+    assumed: HashSet<String>,
+    start: Instant,
+}
+
+impl<'a> ForwardChainer<'a> {
+    pub fn new<I>(map: &'a RuleMap, facts: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let facts: HashSet<String> = facts.into_iter().map(Into::into).collect();
+        let mut tables: Vec<&'a TruthTable> =
+            map.truth_tables().into_iter().map(Arc::as_ref).collect();
+        tables.sort_unstable_by(|a, b| a.rule.cmp(&b.rule));
+        let mut containing: HashMap<&'a str, Vec<usize>> = HashMap::new();
+        for (index, table) in tables.iter().enumerate() {
+            for ident in table.variables.iter() {
+                containing.entry(ident.as_str()).or_default().push(index);
+            }
+        }
+        let mut identifiers: Vec<String> = map.identifiers().cloned().collect();
+        identifiers.extend(facts.iter().cloned());
+        identifiers.sort_unstable();
+        identifiers.dedup();
+        ForwardChainer {
+            map,
+            pending: (0..tables.len()).collect(),
+            tables,
+            containing,
+            identifiers,
+            values: facts.iter().map(|f| (f.clone(), Truth::True)).collect(),
+            facts,
+            reasons: HashMap::new(),
+            assumed: HashSet::new(),
+            start: Instant::now(),
+        }
+    }
+
+    // Adds `ident` as a fact for the next run. The values that may depend on the value
+    // `ident` had are forgotten and the rules containing them are applied again on the next
+    // run, every other value is kept.
+    pub fn assert_fact(&mut self, ident: impl Into<String>) {
+        let ident = ident.into();
+        if !self.facts.insert(ident.clone()) {
+            return;
+        }
+        if let Err(index) = self.identifiers.binary_search(&ident) {
+            self.identifiers.insert(index, ident.clone());
+        }
+        let derived =
+            self.values.get(&ident) == Some(&Truth::True) && !self.assumed.contains(&ident);
+        self.reasons.remove(&ident);
+        if derived {
+            return;
+        }
+        // A value can depend on any other identifier of the rules containing it
+        let mut forgotten = vec![ident.clone()];
+        while let Some(forgotten_ident) = forgotten.pop() {
+            let tables = self
+                .containing
+                .get(forgotten_ident.as_str())
+                .into_iter()
+                .flatten();
+            for &index in tables {
+                self.pending.insert(index);
+                for v in self.tables[index].variables.iter() {
+                    if !self.facts.contains(v) && self.values.remove(v).is_some() {
+                        self.reasons.remove(v);
+                        self.assumed.remove(v);
+                        forgotten.push(v.clone());
+                    }
+                }
+            }
+        }
+        self.assumed.remove(&ident);
+        self.set(&ident, Truth::True);
+    }
+
+    // Derives the closure of the facts, returning every identifier with its value ordered
+    // by identifier.
+    pub fn run(&mut self) -> Result<Vec<(String, Truth)>, ExpertSystemError> {
+        self.start = Instant::now();
+        // Tables without identifiers are never queued again, they are checked on every run
+        let mut applied: BTreeSet<usize> = (0..self.tables.len())
+            .filter(|index| self.tables[*index].variables.is_empty())
+            .collect();
+        loop {
+            self.map.limits().check_timeout(self.start)?;
+            if self.propagate(&mut applied)? || self.settle() {
+                continue;
+            }
+            match self
+                .identifiers
+                .iter()
+                .find(|i| !self.values.contains_key(*i))
+            {
+                Some(ident) => {
+                    let ident = ident.clone();
+                    self.set(&ident, Truth::False);
+                    self.assumed.insert(ident);
+                }
+                None => break,
+            }
+        }
+        // The known values of the other tables did not change since they were satisfied
+        for table in applied.into_iter().map(|index| self.tables[index]) {
+            let satisfiable = (0..table.results.len())
+                .filter(|row| table.results[*row])
+                .any(|row| {
+                    table.variables.iter().enumerate().all(|(i, v)| {
+                        self.known(v)
+                            .is_none_or(|value| table.value(row, i) == value)
+                    })
+                });
+            if !satisfiable {
+                return Err(Contradiction {
+                    rules: vec![table.rule.clone()],
+                    assignment: self.assignment(table),
+                }
+                .into());
+            }
+        }
+        Ok(self
+            .identifiers
+            .iter()
+            .map(|i| (i.clone(), self.value(i)))
+            .collect())
+    }
+
+    // Returns the value of `ident`, identifiers that were not derived are false
+    pub fn value(&self, ident: &str) -> Truth {
+        self.values.get(ident).copied().unwrap_or(Truth::False)
+    }
+
+    // Returns the identifiers that are true without being a fact, ordered by identifier
+    pub fn derived(&self) -> Vec<&str> {
+        let mut derived: Vec<&str> = self
+            .values
+            .iter()
+            .filter(|(ident, value)| **value == Truth::True && !self.facts.contains(*ident))
+            .map(|(ident, _)| ident.as_str())
+            .collect();
+        derived.sort_unstable();
+        derived
+    }
+
+    // Returns the rule that decided the value of `ident`, None for facts and identifiers
+    // that were settled or assumed.
+    pub fn reason(&self, ident: &str) -> Option<&'a TruthTable> {
+        self.reasons.get(ident).copied()
+    }
+
+    fn known(&self, ident: &str) -> Option<bool> {
+        match self.values.get(ident) {
+            Some(Truth::True) => Some(true),
+            Some(Truth::False) => Some(false),
+            _ => None,
+        }
+    }
+
+    // Returns for each group of rows that agree with the known values whether `ident` can
+    // be false or true in it. Rows are grouped by the values of the open identifiers.
+    fn groups(&self, table: &TruthTable, ident: &str) -> HashMap<usize, (bool, bool)> {
+        let index = table.variables.iter().position(|v| v == ident).unwrap();
+        let known: Vec<Option<bool>> = table
+            .variables
+            .iter()
+            .map(|v| if v == ident { None } else { self.known(v) })
+            .collect();
+        let mut groups: HashMap<usize, (bool, bool)> = HashMap::new();
+        for (row, _) in table.results.iter().enumerate().filter(|(_, r)| **r) {
+            let consistent = known
+                .iter()
+                .enumerate()
+                .all(|(i, k)| k.is_none_or(|k| k == table.value(row, i)));
+            if consistent {
+                let group = groups.entry(row & !(1 << (table.variables.len() - 1 - index)));
+                let group = group.or_insert((false, false));
+                match table.value(row, index) {
+                    true => group.1 = true,
+                    false => group.0 = true,
+                }
+            }
+        }
+        groups
+    }
+
+    // Returns the known values of the identifiers of `table`, ordered by identifier
+    fn assignment(&self, table: &TruthTable) -> Vec<(String, bool)> {
+        table
+            .variables
+            .iter()
+            .filter_map(|v| self.known(v).map(|value| (v.clone(), value)))
+            .collect()
+    }
+
+    // Gives `ident` a value and marks the tables containing it to be applied again
+    fn set(&mut self, ident: &str, value: Truth) {
+        self.values.insert(ident.to_string(), value);
+        if let Some(tables) = self.containing.get(ident) {
+            self.pending.extend(tables.iter().copied());
+        }
+    }
+
+    // Applies the pending tables until none of them changes a value, returns whether any
+    // value changed. Every applied table is added to `applied`.
+    fn propagate(&mut self, applied: &mut BTreeSet<usize>) -> Result<bool, ExpertSystemError> {
+        let mut changed = false;
+        while let Some(index) = self.pending.pop_first() {
+            self.map.limits().check_timeout(self.start)?;
+            applied.insert(index);
+            let table = self.tables[index];
+            for ident in table.variables.iter() {
+                if table.sources(ident).is_none() {
+                    continue;
+                }
+                let groups = self.groups(table, ident);
+                let possible = groups
+                    .values()
+                    .fold((false, false), |a, g| (a.0 || g.0, a.1 || g.1));
+                let value = match possible {
+                    (false, false) => {
+                        return Err(Contradiction {
+                            rules: vec![table.rule.clone()],
+                            assignment: self.assignment(table),
+                        }
+                        .into())
+                    }
+                    (false, true) => true,
+                    (true, false) => false,
+                    _ => continue,
+                };
+                match self.known(ident) {
+                    Some(known) if known == value => {}
+                    Some(_) if !self.assumed.remove(ident) => {
+                        return Err(self.contradiction(table, ident).into())
+                    }
+                    _ => {
+                        self.set(ident, Truth::from(value));
+                        self.reasons.insert(ident.clone(), table);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    // Contradiction between `table` and the value `ident` already has
+    fn contradiction(&self, table: &TruthTable, ident: &str) -> Contradiction {
+        let mut rules = vec![];
+        let mut assignment = self.assignment(table);
+        if let Some(reason) = self.reasons.get(ident) {
+            rules.push(reason.rule.clone());
+            assignment.extend(self.assignment(reason));
+        }
+        assignment.retain(|(v, _)| v != ident || self.facts.contains(v));
+        rules.push(table.rule.clone());
+        rules.sort_unstable();
+        assignment.sort_unstable();
+        assignment.dedup();
+        Contradiction { rules, assignment }
+    }
+
+    // Gives a value to the identifiers without one whose tables all have a value for their
+    // sources, returns whether any identifier was settled. Identifiers that become false
+    // are settled before undetermined ones, since their values may decide the others.
+    fn settle(&mut self) -> bool {
+        let mut settled = vec![];
+        for ident in self.identifiers.iter() {
+            if self.values.contains_key(ident) {
+                continue;
+            }
+            let tables = self.containing.get(ident.as_str()).into_iter().flatten();
+            let mut deriving = tables
+                .map(|index| self.tables[*index])
+                .filter_map(|t| Some((t, t.sources(ident)?)));
+            let mut undetermined = false;
+            let complete = deriving.all(|(table, sources)| {
+                let complete = sources
+                    .iter()
+                    .all(|s| s == ident || self.values.contains_key(s));
+                undetermined |= complete
+                    && self
+                        .groups(table, ident)
+                        .values()
+                        .any(|g| *g == (false, true));
+                complete
+            });
+            if complete {
+                let value = match undetermined {
+                    true => Truth::Undetermined,
+                    false => Truth::False,
+                };
+                settled.push((ident.clone(), value));
+            }
+        }
+        if settled.iter().any(|(_, value)| *value == Truth::False) {
+            settled.retain(|(_, value)| *value == Truth::False);
+        }
+        let changed = !settled.is_empty();
+        for (ident, value) in settled {
+            self.set(&ident, value);
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests_forward_chainer {
+    use super::*;
+    use error::Limit;
+    use limits::Limits;
+    use parser::Grammar;
+    use resolver::Resolver;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn run(rules: Vec<&str>, facts: &str) -> Result<Vec<(String, Truth)>> {
+        let map = RuleMap::try_from(rules)?;
+        Ok(ForwardChainer::new(&map, facts.chars()).run()?)
+    }
+
+    fn values(rules: Vec<&str>, facts: &str) -> Result<String> {
+        let result: Vec<String> = run(rules, facts)?
+            .into_iter()
+            .map(|(ident, value)| format!("{}={}", ident, value))
+            .collect();
+        Ok(result.join(" "))
+    }
+
+    #[test]
+    fn chain() -> Result<()> {
+        assert_eq!(
+            values(vec!["A => B", "B => C", "C + D => E"], "A")?,
+            "A=true B=true C=true D=false E=false"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn derived() -> Result<()> {
+        let map = RuleMap::try_from(vec!["A => B", "B => C", "D => E"])?;
+        let mut chainer = ForwardChainer::new(&map, ["A"]);
+        chainer.run()?;
+        assert_eq!(chainer.derived(), vec!["B", "C"]);
+        assert_eq!(chainer.reason("C").map(|t| t.rule.as_str()), Some("B => C"));
+        assert!(chainer.reason("A").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn negation() -> Result<()> {
+        assert_eq!(values(vec!["!A => B"], "")?, "A=false B=true");
+        assert_eq!(
+            values(vec!["C => A", "!A => B"], "")?,
+            "A=false B=true C=false"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn undetermined() -> Result<()> {
+        assert_eq!(
+            values(vec!["A => B | C"], "A")?,
+            "A=true B=undetermined C=undetermined"
+        );
+        assert_eq!(
+            values(vec!["A => B | C", "D => !B"], "AD")?,
+            "A=true B=false C=true D=true"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cycle() -> Result<()> {
+        assert_eq!(values(vec!["A => B", "B => A"], "")?, "A=false B=false");
+        assert_eq!(values(vec!["A => B", "B => A"], "B")?, "A=true B=true");
+        Ok(())
+    }
+
+    #[test]
+    fn same_as_backward() -> Result<()> {
+        let cases: Vec<(Vec<&str>, &str)> = vec![
+            (vec!["A + B => C", "C | D => E", "E ^ F => G"], "ABF"),
+            (vec!["A <=> B", "B => C + !D", "D | C => E"], "A"),
+            (vec!["A | B => C", "C => D | E", "!D => F"], "B"),
+            (vec!["B + C => A", "D | E => B", "B => C"], "E"),
+        ];
+        for (rules, facts) in cases {
+            let map = RuleMap::try_from(rules)?;
+            let forward = ForwardChainer::new(&map, facts.chars()).run()?;
+            let mut resolver = Resolver::new(&map, facts.chars());
+            for (ident, value) in forward {
+                assert_eq!((ident.clone(), resolver.resolve(&ident)?), (ident, value));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn assert_fact() -> Result<()> {
+        let map = RuleMap::try_from(vec!["A => B", "B => C", "D => E", "!F => G"])?;
+        let mut chainer = ForwardChainer::new(&map, "A".chars());
+        chainer.run()?;
+        assert_eq!(chainer.derived(), vec!["B", "C", "G"]);
+
+        // Only the rule containing F is applied again
+        chainer.assert_fact("F");
+        assert_eq!(chainer.pending, BTreeSet::from([0]));
+        chainer.run()?;
+        assert_eq!(chainer.value("G"), Truth::False);
+        assert_eq!(chainer.derived(), vec!["B", "C"]);
+
+        // A derived identifier becomes a fact without changing any value
+        chainer.assert_fact("C");
+        assert!(chainer.pending.is_empty());
+        assert_eq!(chainer.derived(), vec!["B"]);
+
+        chainer.assert_fact("Z");
+        assert_eq!(chainer.run()?.last(), Some(&("Z".to_string(), Truth::True)));
+        Ok(())
+    }
+
+    #[test]
+    fn assert_fact_same_as_new() -> Result<()> {
+        let cases: Vec<(Vec<&str>, &str)> = vec![
+            (vec!["A + B => C", "C | D => E", "E ^ F => G"], "ABF"),
+            (vec!["A <=> B", "B => C + !D", "D | C => E"], "CA"),
+            (vec!["A | B => C", "C => D | E", "!D => F"], "BD"),
+            (vec!["B + C => A", "D | E => B", "B => C", "A => D"], "EC"),
+            (vec!["!A => B", "B => C", "C => A | D"], "CA"),
+        ];
+        for (rules, facts) in cases {
+            let map = RuleMap::try_from(rules)?;
+            let mut chainer = ForwardChainer::new(&map, Vec::<String>::new());
+            chainer.run()?;
+            for fact in facts.chars() {
+                chainer.assert_fact(fact);
+                chainer.run()?;
+            }
+            let mut fresh = ForwardChainer::new(&map, facts.chars());
+            assert_eq!(chainer.run()?, fresh.run()?, "{}", facts);
+            assert_eq!(chainer.derived(), fresh.derived());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn assert_fact_contradiction() -> Result<()> {
+        let map = RuleMap::try_from(vec!["A => !B", "C => D"])?;
+        let mut chainer = ForwardChainer::new(&map, "A".chars());
+        chainer.run()?;
+        chainer.assert_fact("B");
+        assert_eq!(
+            chainer.run().unwrap_err().to_string(),
+            "Contradiction in 'A => !B' when A is true, B is true"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn contradiction() {
+        let result = run(vec!["A => C", "B => !C"], "AB");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Contradiction in 'A => C' and 'B => !C' when A is true, B is true"
+        );
+
+        let result = run(vec!["A => !B"], "AB");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Contradiction in 'A => !B' when A is true, B is true"
+        );
+
+        let result = run(vec!["A + B => 0"], "AB");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Contradiction in 'A + B => 0' when A is true, B is true"
+        );
+
+        let result = run(vec!["1 => 0", "A => B"], "A");
+        assert_eq!(result.unwrap_err().to_string(), "Contradiction in '1 => 0'");
+    }
+
+    #[test]
+    fn error_timeout() -> Result<()> {
+        let limits = Limits {
+            timeout: Some(Duration::ZERO),
+            ..Limits::default()
+        };
+        let mut map = RuleMap::with_limits(Grammar::default(), limits);
+        map.insert("A => B")?;
+        let result = ForwardChainer::new(&map, "A".chars()).run();
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::Timeout,
+                max: 0,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn timeout_per_run() -> Result<()> {
+        let limits = Limits {
+            timeout: Some(Duration::from_millis(50)),
+            ..Limits::default()
+        };
+        let mut map = RuleMap::with_limits(Grammar::default(), limits);
+        map.insert_vec(vec!["A => B", "C => D"])?;
+        let mut chainer = ForwardChainer::new(&map, "A".chars());
+        chainer.run()?;
+        // The timeout is counted from the start of each run, not from the creation
+        std::thread::sleep(Duration::from_millis(100));
+        chainer.assert_fact("C");
+        chainer.run()?;
+        assert_eq!(chainer.derived(), vec!["B", "D"]);
+        Ok(())
+    }
+}
@@ -1,14 +1,48 @@
 use crate::*;
 
-use anyhow::{anyhow, Context, Result};
+use diagnostics::Location;
+use error::{ExpertSystemError, InputError};
+use parser::{RuleParser, Span};
+
 use core::fmt;
-use std::{borrow::Borrow, collections::HashSet, path::PathBuf};
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
-#[derive(PartialEq)]
+// Input holds one set of rules and the scenarios to answer with them. Every line of facts
+// starts a new scenario, the query lines that follow it are answered against those facts.
+// `locations` holds where each rule was read from, when the input was read from lines.
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Input {
     pub rules: Vec<String>,
-    pub facts: String,
-    pub queries: String,
+    pub scenarios: Vec<Scenario>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub locations: Vec<Location>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scenario {
+    pub facts: Vec<String>,
+    pub queries: Vec<String>,
+}
+
+impl Scenario {
+    pub fn new(facts: Vec<String>, queries: Vec<String>) -> Self {
+        Scenario { facts, queries }
+    }
+}
+
+// Inputs are equal when they have the same rules and scenarios, wherever they were read from
+impl PartialEq for Input {
+    fn eq(&self, other: &Self) -> bool {
+        self.rules == other.rules && self.scenarios == other.scenarios
+    }
 }
 
 impl fmt::Debug for Input {
@@ -17,19 +51,38 @@ impl fmt::Debug for Input {
         for rule in self.rules.iter() {
             writeln!(f, "  {}", rule)?;
         }
-        writeln!(f, "Facts: {}", self.facts)?;
-        writeln!(f, "Queries: {}", self.queries)?;
+        for scenario in self.scenarios.iter() {
+            writeln!(f, "Facts: {}", format_identifiers(&scenario.facts))?;
+            writeln!(f, "Queries: {}", format_identifiers(&scenario.queries))?;
+        }
         Ok(())
     }
 }
 
 impl TryFrom<PathBuf> for Input {
-    type Error = anyhow::Error;
+    type Error = ExpertSystemError;
 
     fn try_from(file_path: PathBuf) -> Result<Self, Self::Error> {
-        let content: Vec<String> = read_file(&file_path)
-            .context(format!("Failed to read input file: '{:?}'", file_path))?;
-        Self::try_from(content)
+        let content = match fs::read_to_string(&file_path) {
+            Ok(content) => content,
+            Err(source) => {
+                return Err(ExpertSystemError::Io {
+                    path: file_path,
+                    source,
+                    location: None,
+                })
+            }
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        let mut including: Vec<PathBuf> = fs::canonicalize(&file_path).into_iter().collect();
+        let mut source = vec![];
+        expand_includes(
+            &lines,
+            &file_path.display().to_string(),
+            &mut including,
+            &mut source,
+        )?;
+        Self::from_source(&source)
     }
 }
 
@@ -37,61 +90,295 @@ impl<T> TryFrom<Vec<T>> for Input
 where
     T: Borrow<str>,
 {
-    type Error = anyhow::Error;
+    type Error = ExpertSystemError;
 
     fn try_from(lines: Vec<T>) -> Result<Self, Self::Error> {
-        let mut lines = sanitize::sanitize_lines(&lines);
+        Self::from_lines(&lines, "<input>")
+    }
+}
+
+impl Input {
+    // Reads the rules and scenarios from the lines of `file`. Included files are resolved
+    // relative to the directory of `file`, which is the current directory for names such as
+    // `<stdin>`.
+    pub fn from_lines<T>(lines: &[T], file: &str) -> Result<Self, ExpertSystemError>
+    where
+        T: Borrow<str>,
+    {
+        let mut source = vec![];
+        expand_includes(lines, file, &mut vec![], &mut source)?;
+        Self::from_source(&source)
+    }
 
+    // The syntax of each rule is checked here since only the original lines can tell where
+    // an error is, errors in a line are returned with their Location. Query lines before
+    // the first line of facts belong to the first scenario.
+    fn from_source(lines: &[SourceLine]) -> Result<Self, ExpertSystemError> {
         let mut rules: Vec<String> = vec![];
-        let mut facts: Option<String> = None;
-        let mut queries: Option<String> = None;
-        for line in lines.iter_mut() {
-            match line {
-                l if l.starts_with('=') || l.starts_with('?') => match l.remove(0) {
-                    '=' => match facts {
-                        None => facts = Some(l.to_string()),
-                        Some(_) => return Err(anyhow!("Multiple facts found in input file")),
-                    },
-                    '?' => match queries {
-                        None => queries = Some(l.to_string()),
-                        Some(_) => return Err(anyhow!("Multiple queries found in input file")),
-                    },
-                    _ => unreachable!(),
-                },
-                l if !l.is_empty() => rules.push(l.to_string()),
-                _ => continue,
+        let mut locations: Vec<Location> = vec![];
+        // Every scenario with the line of its facts and whether it has a line of queries
+        let mut scenarios: Vec<(Scenario, usize, bool)> = vec![];
+        let mut leading: Option<Vec<String>> = None;
+        for (index, source) in lines.iter().enumerate() {
+            let (line, columns) = sanitize::sanitize_line(&source.text);
+            let locate =
+                |span: Span| -> Location { source.locate(original_columns(&columns, span)) };
+            let error = |error: InputError, span: Span| ExpertSystemError::Input {
+                error,
+                location: Some(Box::new(locate(span))),
+            };
+            let invalid: fn(String) -> InputError = match line.chars().next() {
+                Some('=') => InputError::InvalidFact,
+                Some('?') => InputError::InvalidQuery,
+                Some(_) => {
+                    if let Err(err) = RuleParser::new().parse(&line) {
+                        return Err(err.locate(locate));
+                    }
+                    locations.push(locate(Span::new(0, line.chars().count())));
+                    rules.push(line);
+                    continue;
+                }
+                None => continue,
+            };
+            let identifiers = parse_identifiers(&line[1..], invalid).map_err(|(err, part)| {
+                let start = line[1..]
+                    .find(&part)
+                    .map_or(1, |i| line[..1 + i].chars().count());
+                let span = Span::new(start, start + part.chars().count());
+                error(err, span)
+            })?;
+            if line.starts_with('=') {
+                scenarios.push((Scenario::new(identifiers, vec![]), index, false));
+                continue;
             }
+            let queries = match scenarios.last_mut() {
+                Some((scenario, _, has_queries)) => {
+                    *has_queries = true;
+                    &mut scenario.queries
+                }
+                None => leading.get_or_insert_with(Vec::new),
+            };
+            extend_unique(queries, identifiers);
         }
 
-        let facts = facts.context("No facts in input file")?;
-        if let Some(c) = facts.chars().find(|c| !is_identifier(c)) {
-            return Err(anyhow!("Invalid identifier in facts: '{}'", c));
+        if scenarios.is_empty() {
+            return Err(InputError::NoFacts.into());
         }
-        let queries = queries.context("No queries in input file")?;
-        if let Some(c) = queries.chars().find(|c| !is_identifier(c)) {
-            return Err(anyhow!("Invalid identifier in query: '{}'", c));
+        if let Some(leading) = leading {
+            let (first, _, has_queries) = &mut scenarios[0];
+            let queries = std::mem::replace(&mut first.queries, leading);
+            extend_unique(&mut first.queries, queries);
+            *has_queries = true;
+        } else if scenarios.iter().all(|(_, _, has_queries)| !has_queries) {
+            return Err(InputError::NoQueries.into());
+        }
+        if let Some((_, index, _)) = scenarios.iter().find(|(_, _, has_queries)| !has_queries) {
+            let (_, columns) = sanitize::sanitize_line(&lines[*index].text);
+            let columns = original_columns(&columns, Span::new(0, 1));
+            return Err(ExpertSystemError::Input {
+                error: InputError::MissingQueries,
+                location: Some(Box::new(lines[*index].locate(columns))),
+            });
         }
-
-        let mut fact_set = HashSet::new();
-        let mut queries_set = HashSet::new();
         Ok(Input {
             rules,
-            facts: facts
-                .chars()
-                .filter(|c| fact_set.insert(c.to_owned()))
-                .collect(),
-            queries: queries
-                .chars()
-                .filter(|c| queries_set.insert(c.to_owned()))
-                .collect(),
+            scenarios: scenarios.into_iter().map(|(s, _, _)| s).collect(),
+            locations,
         })
     }
 }
 
+// Line of an input with the file and line number it was read from
+struct SourceLine {
+    file: String,
+    number: usize,
+    text: String,
+}
+
+impl SourceLine {
+    fn locate(&self, columns: Range<usize>) -> Location {
+        Location::new(self.file.clone(), self.number, self.text.clone(), columns)
+    }
+}
+
+// Appends the lines of `file` to `source`, replacing every `#include "path"` line with the
+// lines of that file. `including` holds the canonical paths of the files that are being
+// read, an include of one of them would never end.
+fn expand_includes<T>(
+    lines: &[T],
+    file: &str,
+    including: &mut Vec<PathBuf>,
+    source: &mut Vec<SourceLine>,
+) -> Result<(), ExpertSystemError>
+where
+    T: Borrow<str>,
+{
+    for (index, text) in lines.iter().enumerate() {
+        let line = SourceLine {
+            file: file.to_string(),
+            number: index + 1,
+            text: text.borrow().to_string(),
+        };
+        let (path, columns) = match parse_include(&line.text) {
+            Some(Include::Path(path, columns)) => (path, columns),
+            Some(Include::Invalid(columns)) => {
+                return Err(ExpertSystemError::Input {
+                    error: InputError::InvalidInclude,
+                    location: Some(Box::new(line.locate(columns))),
+                })
+            }
+            None => {
+                source.push(line);
+                continue;
+            }
+        };
+        let path = Path::new(file).parent().unwrap_or(Path::new("")).join(path);
+        let io_error = |source| ExpertSystemError::Io {
+            path: path.clone(),
+            source,
+            location: Some(Box::new(line.locate(columns.clone()))),
+        };
+        let canonical = fs::canonicalize(&path).map_err(io_error)?;
+        if including.contains(&canonical) {
+            return Err(ExpertSystemError::Input {
+                error: InputError::IncludeCycle(path),
+                location: Some(Box::new(line.locate(columns))),
+            });
+        }
+        let content = fs::read_to_string(&path).map_err(io_error)?;
+        let included: Vec<&str> = content.lines().collect();
+        including.push(canonical);
+        expand_includes(&included, &path.display().to_string(), including, source)?;
+        including.pop();
+    }
+    Ok(())
+}
+
+// `#include` directive with the columns of its quoted path, or of the whole directive when
+// it is invalid
+#[derive(Debug, PartialEq)]
+enum Include {
+    Path(String, Range<usize>),
+    Invalid(Range<usize>),
+}
+
+// Reads an `#include "path"` directive, which may be followed by a comment. Returns None
+// for any other line.
+fn parse_include(line: &str) -> Option<Include> {
+    let column = |rest: &str| line.chars().count() - rest.chars().count();
+    let directive = line.trim_start();
+    let rest = directive.strip_prefix("#include")?;
+    if !rest.is_empty() && !rest.starts_with(|c: char| c.is_whitespace() || c == '"') {
+        return None;
+    }
+    let invalid = Include::Invalid(column(directive)..line.trim_end().chars().count());
+    let quoted = match rest.trim_start().strip_prefix('"') {
+        Some(quoted) => quoted,
+        None => return Some(invalid),
+    };
+    let (path, tail) = match quoted.split_once('"') {
+        Some((path, tail)) if !path.is_empty() => (path, tail.trim_start()),
+        _ => return Some(invalid),
+    };
+    if !tail.is_empty() && !tail.starts_with('#') {
+        return Some(invalid);
+    }
+    let start = column(quoted) - 1;
+    let columns = start..start + path.chars().count() + 2;
+    Some(Include::Path(path.to_string(), columns))
+}
+
+// Adds the identifiers that `target` does not contain yet
+fn extend_unique(target: &mut Vec<String>, identifiers: Vec<String>) {
+    for ident in identifiers {
+        if !target.contains(&ident) {
+            target.push(ident);
+        }
+    }
+}
+
+// Maps a span of a sanitized line to the columns of the original line, a span past the end
+// of the line points right after its last character.
+pub(crate) fn original_columns(columns: &[usize], span: Span) -> Range<usize> {
+    let after = columns.last().map_or(0, |c| c + 1);
+    let start = columns.get(span.start).copied().unwrap_or(after);
+    let end = span
+        .end
+        .checked_sub(1)
+        .and_then(|i| columns.get(i))
+        .map_or(after, |c| c + 1);
+    start..end.max(start + 1)
+}
+
+// Splits a line of facts or queries into identifiers and removes duplicates. Identifiers
+// are separated by whitespace or commas. In a line without commas a group of uppercase
+// letters is read as single letter identifiers the way older versions did, `ABG` and `AB G`
+// are both `A`, `B` and `G`. A comma marks a line in which every group is one identifier, so
+// identifiers of several uppercase letters are written as `ABS,` or `AB, CD`.
+//
+// A line without commas that has both a group of uppercase letters and an identifier that
+// is not only uppercase letters (`AB Cd`) is refused as ambiguous, the group could be meant
+// either way. Errors are returned with the part of the line they are about, `invalid` makes
+// the error for a part that is not an identifier.
+pub fn parse_identifiers(
+    line: &str,
+    invalid: fn(String) -> InputError,
+) -> Result<Vec<String>, (InputError, String)> {
+    let groups: Vec<&str> = line
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|group| !group.is_empty())
+        .collect();
+    if let Some(group) = groups.iter().find(|group| !is_identifier(group)) {
+        return Err((invalid(group.to_string()), group.to_string()));
+    }
+    let letters = |group: &str| group.chars().all(|c| c.is_ascii_uppercase());
+    let parts: Vec<String> = match line.contains(',') {
+        true => groups.iter().map(|group| group.to_string()).collect(),
+        false => {
+            let grouped = groups.iter().find(|g| g.len() > 1 && letters(g));
+            if let (Some(group), true) = (grouped, groups.iter().any(|g| !letters(g))) {
+                let error = InputError::AmbiguousIdentifiers(group.to_string());
+                return Err((error, group.to_string()));
+            }
+            groups
+                .iter()
+                .flat_map(|group| match letters(group) {
+                    true => group.chars().map(String::from).collect(),
+                    false => vec![group.to_string()],
+                })
+                .collect()
+        }
+    };
+    let mut set = HashSet::new();
+    Ok(parts
+        .into_iter()
+        .filter(|part| set.insert(part.clone()))
+        .collect())
+}
+
+// Formats identifiers the way parse_identifiers reads them, single uppercase letters are
+// written without separators. Identifiers of several uppercase letters are separated by
+// commas, with a trailing comma when there is only one.
+pub fn format_identifiers(identifiers: &[String]) -> String {
+    let letters = |ident: &str| ident.chars().all(|c| c.is_ascii_uppercase());
+    if identifiers
+        .iter()
+        .all(|ident| ident.len() == 1 && letters(ident))
+    {
+        return identifiers.concat();
+    }
+    match identifiers.iter().any(|i| i.len() > 1 && letters(i)) {
+        true if identifiers.len() == 1 => format!("{},", identifiers[0]),
+        true => identifiers.join(", "),
+        false => identifiers.join(" "),
+    }
+}
+
 #[cfg(test)]
 mod tests_input {
     use super::*;
     use crate::test_utils;
+    use error::ParseError;
 
     use anyhow::Result;
     use pretty_assertions::assert_eq;
@@ -104,8 +391,8 @@ mod tests_input {
             result,
             Input {
                 rules: vec!["A=>Z".to_string()],
-                facts: "A".to_string(),
-                queries: "Z".to_string(),
+                scenarios: vec![Scenario::new(vec!["A".to_string()], vec!["Z".to_string()])],
+                ..Default::default()
             }
         );
         Ok(())
@@ -114,8 +401,11 @@ mod tests_input {
     #[test]
     fn error_from_file_non_exist() {
         let input_file = test_utils::input_file_path("input/non_exist.txt");
-        let result = Input::try_from(input_file);
-        assert!(result.is_err());
+        let result = Input::try_from(input_file.clone());
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::Io { path, .. }) if path == input_file
+        ));
     }
 
     #[test]
@@ -124,8 +414,8 @@ mod tests_input {
             Input::try_from(vec!["A=>Z", "=A", "?Z"])?,
             Input {
                 rules: vec!["A=>Z".to_string()],
-                facts: "A".to_string(),
-                queries: "Z".to_string(),
+                scenarios: vec![Scenario::new(vec!["A".to_string()], vec!["Z".to_string()])],
+                ..Default::default()
             }
         );
         Ok(())
@@ -137,8 +427,8 @@ mod tests_input {
             Input::try_from(vec!["?Z", "=A", "A=>Z"])?,
             Input {
                 rules: vec!["A=>Z".to_string()],
-                facts: "A".to_string(),
-                queries: "Z".to_string(),
+                scenarios: vec![Scenario::new(vec!["A".to_string()], vec!["Z".to_string()])],
+                ..Default::default()
             }
         );
         Ok(())
@@ -150,8 +440,8 @@ mod tests_input {
             Input::try_from(vec!["A=>Z", "=A", "Z=>A", "?Z"])?,
             Input {
                 rules: vec!["A=>Z".to_string(), "Z=>A".to_string()],
-                facts: "A".to_string(),
-                queries: "Z".to_string(),
+                scenarios: vec![Scenario::new(vec!["A".to_string()], vec!["Z".to_string()])],
+                ..Default::default()
             }
         );
         Ok(())
@@ -163,8 +453,8 @@ mod tests_input {
             Input::try_from(vec!["=A", "?Z"])?,
             Input {
                 rules: vec![],
-                facts: "A".to_string(),
-                queries: "Z".to_string(),
+                scenarios: vec![Scenario::new(vec!["A".to_string()], vec!["Z".to_string()])],
+                ..Default::default()
             }
         );
         Ok(())
@@ -176,8 +466,8 @@ mod tests_input {
             Input::try_from(vec!["A=>Z", "=A", "?Z"])?,
             Input {
                 rules: vec!["A=>Z".to_string()],
-                facts: "A".to_string(),
-                queries: "Z".to_string(),
+                scenarios: vec![Scenario::new(vec!["A".to_string()], vec!["Z".to_string()])],
+                ..Default::default()
             }
         );
         Ok(())
@@ -189,8 +479,8 @@ mod tests_input {
             Input::try_from(vec!["=", "?Z"])?,
             Input {
                 rules: vec![],
-                facts: "".to_string(),
-                queries: "Z".to_string(),
+                scenarios: vec![Scenario::new(vec![], vec!["Z".to_string()])],
+                ..Default::default()
             }
         );
         Ok(())
@@ -202,8 +492,8 @@ mod tests_input {
             Input::try_from(vec!["=A", "?"])?,
             Input {
                 rules: vec![],
-                facts: "A".to_string(),
-                queries: "".to_string(),
+                scenarios: vec![Scenario::new(vec!["A".to_string()], vec![])],
+                ..Default::default()
             }
         );
         Ok(())
@@ -215,8 +505,8 @@ mod tests_input {
             Input::try_from(vec!["=AA", "?"])?,
             Input {
                 rules: vec![],
-                facts: "A".to_string(),
-                queries: "".to_string(),
+                scenarios: vec![Scenario::new(vec!["A".to_string()], vec![])],
+                ..Default::default()
             }
         );
         Ok(())
@@ -228,51 +518,384 @@ mod tests_input {
             Input::try_from(vec!["=", "?ZZ"])?,
             Input {
                 rules: vec![],
-                facts: "".to_string(),
-                queries: "Z".to_string(),
+                scenarios: vec![Scenario::new(vec![], vec!["Z".to_string()])],
+                ..Default::default()
             }
         );
         Ok(())
     }
 
+    #[test]
+    fn long_identifiers() -> Result<()> {
+        assert_eq!(
+            Input::try_from(vec![
+                "HasFever + sensor.3.ok => engine_overheat",
+                "= HasFever, sensor.3.ok",
+                "?engine_overheat A"
+            ])?,
+            Input {
+                rules: vec!["HasFever+sensor.3.ok=>engine_overheat".to_string()],
+                scenarios: vec![Scenario::new(
+                    vec!["HasFever".to_string(), "sensor.3.ok".to_string()],
+                    vec!["engine_overheat".to_string(), "A".to_string()]
+                )],
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn identifiers() {
+        let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        let parse = |line: &str| parse_identifiers(line, InputError::InvalidFact);
+        assert_eq!(parse("ABA"), Ok(strings(&["A", "B"])));
+        assert_eq!(parse(""), Ok(vec![]));
+        assert_eq!(parse("ABS,"), Ok(strings(&["ABS"])));
+        assert_eq!(parse("AB CD"), Ok(strings(&["A", "B", "C", "D"])));
+        assert_eq!(parse("AB, CD"), Ok(strings(&["AB", "CD"])));
+        assert_eq!(parse("AB, Cd"), Ok(strings(&["AB", "Cd"])));
+        assert_eq!(parse("A Cd"), Ok(strings(&["A", "Cd"])));
+        assert_eq!(
+            parse("HasFever,A B HasFever"),
+            Ok(strings(&["HasFever", "A", "B"]))
+        );
+        assert_eq!(
+            parse("A 1B C"),
+            Err((InputError::InvalidFact("1B".to_string()), "1B".to_string()))
+        );
+    }
+
+    #[test]
+    fn error_ambiguous_identifiers() {
+        let parse = |line: &str| parse_identifiers(line, InputError::InvalidQuery);
+        let ambiguous = |group: &str| {
+            let error = InputError::AmbiguousIdentifiers(group.to_string());
+            Err((error, group.to_string()))
+        };
+        assert_eq!(parse("AB Cd"), ambiguous("AB"));
+        assert_eq!(parse("HasFever C ABS"), ambiguous("ABS"));
+
+        let result = Input::try_from(vec!["A => B", "=A", "?B has_fever AB"]).unwrap_err();
+        assert!(matches!(
+            &result,
+            ExpertSystemError::Input {
+                error: InputError::AmbiguousIdentifiers(group),
+                ..
+            } if group == "AB"
+        ));
+        assert_eq!(result.location().map(|l| l.columns.clone()), Some(13..15));
+    }
+
+    #[test]
+    fn format() {
+        let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        assert_eq!(format_identifiers(&strings(&["A", "B"])), "AB");
+        assert_eq!(
+            format_identifiers(&strings(&["A", "HasFever"])),
+            "A HasFever"
+        );
+        assert_eq!(format_identifiers(&strings(&["ABS"])), "ABS,");
+        assert_eq!(format_identifiers(&strings(&["AB", "C"])), "AB, C");
+        assert_eq!(format_identifiers(&strings(&["AB", "Cd"])), "AB, Cd");
+        assert_eq!(format_identifiers(&strings(&["a", "b"])), "a b");
+        assert_eq!(format_identifiers(&[]), "");
+    }
+
+    #[test]
+    fn error_kinds() {
+        let result = Input::try_from(vec!["=A", "?B", "=C"]).unwrap_err();
+        assert!(matches!(
+            result,
+            ExpertSystemError::Input {
+                error: InputError::MissingQueries,
+                ..
+            }
+        ));
+        assert_eq!(result.location().map(|l| l.line), Some(3));
+
+        let result = Input::try_from(vec!["=A", "?B b-c"]).unwrap_err();
+        assert!(matches!(
+            &result,
+            ExpertSystemError::Input {
+                error: InputError::InvalidQuery(ident),
+                ..
+            } if ident == "b-c"
+        ));
+        assert_eq!(result.location().map(|l| l.columns.clone()), Some(3..6));
+
+        let result = Input::try_from(vec!["A => B)", "=A", "?B"]).unwrap_err();
+        assert!(matches!(
+            result,
+            ExpertSystemError::Parse {
+                error: ParseError::UnexpectedToken(_),
+                ..
+            }
+        ));
+        assert_eq!(result.location().map(|l| l.line), Some(1));
+    }
+
+    #[test]
+    fn error_invalid_facts() {
+        let result = Input::try_from(vec!["=A b-c", "?"]);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            indoc! {"
+                Invalid identifier in facts: 'b-c'
+                 --> <input>:1:4
+                  |
+                1 | =A b-c
+                  |    ^^^
+            "}
+        );
+    }
+
+    #[test]
+    fn error_invalid_rule() {
+        let result = Input::try_from(vec!["A => B", "", "  A + $ => C  # comment", "=", "?"]);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            indoc! {"
+                Unexpected character: $
+                 --> <input>:3:7
+                  |
+                3 |   A + $ => C  # comment
+                  |       ^
+            "}
+        );
+    }
+
+    #[test]
+    fn error_incomplete_rule() {
+        let result = Input::try_from(vec!["(A + B => C"]);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            indoc! {"
+                Missing closing parenthesis
+                 --> <input>:1:1
+                  |
+                1 | (A + B => C
+                  | ^
+            "}
+        );
+
+        let result = Input::try_from(vec!["A + B =>  # comment"]);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            indoc! {"
+                Unexpected end of token list
+                 --> <input>:1:9
+                  |
+                1 | A + B =>  # comment
+                  |         ^
+            "}
+        );
+    }
+
     #[test]
     fn error_empty() {
         let result = Input::try_from(Vec::<String>::new());
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err().to_string(), "No facts in input file");
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::Input {
+                error: InputError::NoFacts,
+                location: None
+            })
+        ));
     }
 
     #[test]
     fn error_no_facts() {
         let result = Input::try_from(vec!["?"]);
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err().to_string(), "No facts in input file");
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::Input {
+                error: InputError::NoFacts,
+                location: None
+            })
+        ));
     }
 
     #[test]
     fn error_no_queries() {
         let result = Input::try_from(vec!["="]);
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err().to_string(), "No queries in input file");
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::Input {
+                error: InputError::NoQueries,
+                location: None
+            })
+        ));
     }
 
     #[test]
-    fn error_double_facts() {
-        let result = Input::try_from(vec!["=", "=", "?"]);
+    fn scenarios() -> Result<()> {
+        assert_eq!(
+            Input::try_from(vec!["A=>Z", "=A", "?Z", "=", "?Z A", "?B Z"])?,
+            Input {
+                rules: vec!["A=>Z".to_string()],
+                scenarios: vec![
+                    Scenario::new(vec!["A".to_string()], vec!["Z".to_string()]),
+                    Scenario::new(
+                        vec![],
+                        vec!["Z".to_string(), "A".to_string(), "B".to_string()]
+                    ),
+                ],
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn leading_queries() -> Result<()> {
+        assert_eq!(
+            Input::try_from(vec!["?Z", "=A", "?B", "=B", "?A"])?,
+            Input {
+                rules: vec![],
+                scenarios: vec![
+                    Scenario::new(
+                        vec!["A".to_string()],
+                        vec!["Z".to_string(), "B".to_string()]
+                    ),
+                    Scenario::new(vec!["B".to_string()], vec!["A".to_string()]),
+                ],
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn error_missing_queries() {
+        let result = Input::try_from(vec!["=", "=A", "?"]);
         assert!(result.is_err());
         assert_eq!(
             result.unwrap_err().to_string(),
-            "Multiple facts found in input file"
+            indoc! {"
+                No queries for these facts
+                 --> <input>:1:1
+                  |
+                1 | =
+                  | ^
+            "}
         );
     }
 
     #[test]
-    fn error_double_queries() {
-        let result = Input::try_from(vec!["=", "?", "?"]);
-        assert!(result.is_err());
+    fn include() -> Result<()> {
+        let input_file = test_utils::input_file_path("input/include/main.txt");
+        let result = Input::try_from(input_file)?;
+        assert_eq!(
+            result.rules,
+            vec![
+                "HasFever+Cough=>flu",
+                "flu=>tired",
+                "flu|sensor.3.ok=>!engine_overheat",
+                "flu=>tired"
+            ]
+        );
+        assert_eq!(result.scenarios.len(), 1);
+        let locations: Vec<(bool, usize)> = result
+            .locations
+            .iter()
+            .map(|l| (l.file.ends_with("shared.txt"), l.line))
+            .collect();
+        assert_eq!(
+            locations,
+            vec![(false, 1), (true, 1), (false, 1), (true, 1)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn include_directive() {
+        let path = |p: &str| Some(Include::Path(p.to_string(), 9..9 + p.len() + 2));
+        assert_eq!(parse_include("#include \"a.txt\""), path("a.txt"));
+        assert_eq!(
+            parse_include("#include \"a b.txt\"  # comment"),
+            path("a b.txt")
+        );
+        assert_eq!(
+            parse_include("  #include\"a.txt\""),
+            Some(Include::Path("a.txt".into(), 10..17))
+        );
+        assert_eq!(parse_include("# include \"a.txt\""), None);
+        assert_eq!(parse_include("#included files"), None);
+        assert_eq!(parse_include("A => B # #include \"a.txt\""), None);
+        assert_eq!(
+            parse_include("#include a.txt"),
+            Some(Include::Invalid(0..14))
+        );
+        assert_eq!(
+            parse_include("#include \"a.txt"),
+            Some(Include::Invalid(0..15))
+        );
+        assert_eq!(
+            parse_include("#include \"\""),
+            Some(Include::Invalid(0..11))
+        );
+        assert_eq!(
+            parse_include("#include \"a.txt\" B"),
+            Some(Include::Invalid(0..18))
+        );
+    }
+
+    #[test]
+    fn error_include_cycle() {
+        let input_file = test_utils::input_file_path("input/include/cycle_a.txt");
+        let result = Input::try_from(input_file).unwrap_err();
+        assert!(matches!(
+            &result,
+            ExpertSystemError::Input {
+                error: InputError::IncludeCycle(path),
+                ..
+            } if path.ends_with("cycle_a.txt")
+        ));
+        let location = result.location().unwrap();
+        assert!(location.file.ends_with("cycle_b.txt"));
+        assert_eq!((location.line, location.columns.clone()), (1, 9..22));
+    }
+
+    #[test]
+    fn error_include_missing() {
+        let input_file = test_utils::input_file_path("input/include/missing.txt");
+        let result = Input::try_from(input_file).unwrap_err();
+        assert!(matches!(
+            &result,
+            ExpertSystemError::Io { path, .. } if path.ends_with("missing_domain.txt")
+        ));
+        let location = result.location().unwrap();
+        assert!(location.file.ends_with("missing.txt"));
+        assert_eq!(location.line, 2);
+    }
+
+    #[test]
+    fn error_include_invalid_rule() {
+        let input_file = test_utils::input_file_path("input/include/invalid_rule.txt");
+        let result = Input::try_from(input_file).unwrap_err();
+        assert!(matches!(result, ExpertSystemError::Parse { .. }));
+        let location = result.location().unwrap();
+        assert!(location.file.ends_with("invalid.txt"));
+        assert_eq!(location.line, 2);
+    }
+
+    #[test]
+    fn error_include_directive() {
+        let result = Input::try_from(vec!["#include rules.txt", "=", "?"]);
         assert_eq!(
             result.unwrap_err().to_string(),
-            "Multiple queries found in input file"
+            indoc! {"
+                Expected a quoted path after #include
+                 --> <input>:1:1
+                  |
+                1 | #include rules.txt
+                  | ^^^^^^^^^^^^^^^^^^
+            "}
         );
     }
 }
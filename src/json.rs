@@ -0,0 +1,409 @@
+use crate::*;
+use explain::{Explanation, Reason};
+use lint::Warning;
+use parser::Truth;
+use report::{QueryResult, Report, ScenarioReport};
+
+use std::fmt;
+
+// JSON document. Objects keep their keys in the order they were added, so the output of
+// a type is stable. `{}` formats it on a single line and `{:#}` indents it by two spaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+// Types that can be written as JSON
+pub trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+impl Json {
+    pub fn object<const N: usize>(entries: [(&str, Json); N]) -> Self {
+        Json::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter, depth: Option<usize>) -> fmt::Result {
+        let (open, separator, close) = match depth {
+            Some(depth) => (
+                format!("\n{}", "  ".repeat(depth + 1)),
+                ": ",
+                format!("\n{}", "  ".repeat(depth)),
+            ),
+            None => (String::new(), ":", String::new()),
+        };
+        let inner = depth.map(|d| d + 1);
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(s) => write_string(f, s),
+            Json::Array(items) if items.is_empty() => write!(f, "[]"),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    write!(f, "{}{}", if i > 0 { "," } else { "" }, open)?;
+                    item.fmt_indented(f, inner)?;
+                }
+                write!(f, "{}]", close)
+            }
+            Json::Object(entries) if entries.is_empty() => write!(f, "{{}}"),
+            Json::Object(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    write!(f, "{}{}", if i > 0 { "," } else { "" }, open)?;
+                    write_string(f, key)?;
+                    write!(f, "{}", separator)?;
+                    value.fmt_indented(f, inner)?;
+                }
+                write!(f, "{}}}", close)
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_indented(f, f.alternate().then_some(0))
+    }
+}
+
+// A document is serialized as itself, objects keep the order of their keys
+#[cfg(feature = "serde")]
+impl serde::Serialize for Json {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        match self {
+            Json::Null => serializer.serialize_unit(),
+            Json::Bool(b) => serializer.serialize_bool(*b),
+            Json::Number(n) => serializer.serialize_i64(*n),
+            Json::String(s) => serializer.serialize_str(s),
+            Json::Array(items) => serializer.collect_seq(items),
+            Json::Object(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries.iter() {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> Json {
+        Json::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> Json {
+        self[..].to_json()
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> Json {
+        self.as_ref().map_or(Json::Null, ToJson::to_json)
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> Json {
+        Json::String(self.clone())
+    }
+}
+
+impl ToJson for Truth {
+    fn to_json(&self) -> Json {
+        Json::String(self.to_string())
+    }
+}
+
+impl ToJson for Explanation {
+    fn to_json(&self) -> Json {
+        let (reason, rule, premises) = match &self.reason {
+            Reason::Fact => ("fact", None, &[][..]),
+            Reason::Rule { rule, premises } => ("rule", Some(rule.clone()), &premises[..]),
+            Reason::Default => ("default", None, &[][..]),
+            Reason::Cycle => ("cycle", None, &[][..]),
+        };
+        Json::object([
+            ("ident", self.ident.to_json()),
+            ("value", self.value.to_json()),
+            ("reason", Json::String(reason.to_string())),
+            ("rule", rule.to_json()),
+            ("premises", premises.to_json()),
+        ])
+    }
+}
+
+// Serialized as its ToJson document, so that the schema is only written down once
+#[cfg(feature = "serde")]
+impl serde::Serialize for Explanation {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl ToJson for QueryResult {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("query", self.query.to_json()),
+            ("value", self.value.to_json()),
+            ("explanation", self.explanation.to_json()),
+        ])
+    }
+}
+
+impl ToJson for Warning {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("message", Json::String(self.kind.to_string())),
+            (
+                "file",
+                self.location.as_ref().map(|l| l.file.clone()).to_json(),
+            ),
+            (
+                "line",
+                self.location
+                    .as_ref()
+                    .map_or(Json::Null, |l| Json::Number(l.line as i64)),
+            ),
+        ])
+    }
+}
+
+// Serialized as its ToJson document, see Explanation
+#[cfg(feature = "serde")]
+impl serde::Serialize for Warning {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl ToJson for ScenarioReport {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("facts", self.facts.to_json()),
+            ("queries", self.queries.to_json()),
+            ("derived", self.derived.to_json()),
+        ])
+    }
+}
+
+impl ToJson for Report {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("rules", self.rules.to_json()),
+            ("scenarios", self.scenarios.to_json()),
+            ("warnings", self.warnings.to_json()),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests_json {
+    use super::*;
+    use input::{Input, Scenario};
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn compact() {
+        let result = Json::object([
+            ("a", Json::Null),
+            ("b", Json::Array(vec![Json::Bool(true), Json::Number(-3)])),
+            ("c", Json::Array(vec![])),
+            ("d", Json::Object(vec![])),
+        ]);
+        assert_eq!(
+            result.to_string(),
+            r#"{"a":null,"b":[true,-3],"c":[],"d":{}}"#
+        );
+    }
+
+    #[test]
+    fn pretty() {
+        let result = Json::object([
+            ("rules", Json::Array(vec![Json::String("A=>B".into())])),
+            ("facts", Json::Array(vec![])),
+        ]);
+        assert_eq!(
+            format!("{:#}", result),
+            indoc! {r#"
+                {
+                  "rules": [
+                    "A=>B"
+                  ],
+                  "facts": []
+                }"#}
+        );
+    }
+
+    #[test]
+    fn escape() {
+        let result = Json::String("a\"b\\c\nd\u{1}".to_string());
+        assert_eq!(result.to_string(), r#""a\"b\\c\nd\u0001""#);
+    }
+
+    #[test]
+    fn explanation() {
+        let result = Explanation {
+            ident: "B".to_string(),
+            value: Truth::True,
+            reason: Reason::Rule {
+                rule: "A => B".to_string(),
+                premises: vec![Explanation {
+                    ident: "A".to_string(),
+                    value: Truth::True,
+                    reason: Reason::Fact,
+                }],
+            },
+        };
+        assert_eq!(
+            result.to_json().to_string(),
+            concat!(
+                r#"{"ident":"B","value":"true","reason":"rule","rule":"A => B","premises":["#,
+                r#"{"ident":"A","value":"true","reason":"fact","rule":null,"premises":[]}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn report() {
+        let input = Input {
+            rules: vec!["A => B".to_string()],
+            scenarios: vec![
+                Scenario::new(vec!["A".to_string()], vec!["B".to_string()]),
+                Scenario::new(vec![], vec!["B".to_string()]),
+            ],
+            ..Default::default()
+        };
+        let mut result = Report::new(&input);
+        result.scenarios[0].push("B", Truth::True, None);
+        result.scenarios[1].push("B", Truth::False, None);
+        assert_eq!(
+            format!("{:#}", result.to_json()),
+            indoc! {r#"
+                {
+                  "rules": [
+                    "A => B"
+                  ],
+                  "scenarios": [
+                    {
+                      "facts": [
+                        "A"
+                      ],
+                      "queries": [
+                        {
+                          "query": "B",
+                          "value": "true",
+                          "explanation": null
+                        }
+                      ],
+                      "derived": null
+                    },
+                    {
+                      "facts": [],
+                      "queries": [
+                        {
+                          "query": "B",
+                          "value": "false",
+                          "explanation": null
+                        }
+                      ],
+                      "derived": null
+                    }
+                  ],
+                  "warnings": []
+                }"#}
+        );
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests_serde {
+    use super::*;
+    use diagnostics::Location;
+    use input::{Input, Scenario};
+    use lint::WarningKind;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    // Serde writes a report as the same document as ToJson
+    #[test]
+    fn report() -> Result<()> {
+        let input = Input {
+            rules: vec!["A => B".to_string(), "C => C".to_string()],
+            scenarios: vec![Scenario::new(
+                vec!["A".to_string()],
+                vec!["B".to_string(), "D".to_string()],
+            )],
+            ..Default::default()
+        };
+        let mut report = Report::new(&input);
+        let explanation = Explanation {
+            ident: "B".to_string(),
+            value: Truth::True,
+            reason: Reason::Rule {
+                rule: "A => B".to_string(),
+                premises: vec![Explanation {
+                    ident: "A".to_string(),
+                    value: Truth::True,
+                    reason: Reason::Fact,
+                }],
+            },
+        };
+        report.scenarios[0].push("B", Truth::True, Some(explanation));
+        report.scenarios[0].push("D", Truth::Undetermined, None);
+        report.scenarios[0].derived = Some(vec!["B".to_string()]);
+        report.warnings.push(Warning {
+            kind: WarningKind::Tautology("C => C".to_string()),
+            location: Some(Location {
+                file: "input.txt".to_string(),
+                line: 2,
+                source: "C => C".to_string(),
+                columns: 0..6,
+            }),
+        });
+        assert_eq!(
+            serde_json::to_string(&report)?,
+            report.to_json().to_string()
+        );
+        assert_eq!(
+            serde_json::from_str::<Truth>(r#""undetermined""#)?,
+            Truth::Undetermined
+        );
+        Ok(())
+    }
+}
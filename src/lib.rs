@@ -1,10 +1,26 @@
+pub mod cli;
+pub mod diagnostics;
+pub mod error;
+pub mod explain;
+pub mod expr;
+pub mod formatter;
+pub mod forward;
 pub mod input;
+pub mod json;
+pub mod limits;
+pub mod lint;
+pub mod normal;
 pub mod parser;
-pub mod permutation_iter;
+pub mod query;
+pub mod repl;
+pub mod report;
+pub mod resolver;
 pub mod sanitize;
+pub mod sat;
+pub mod sat_resolver;
+pub mod table;
 
 use anyhow::{anyhow, Context, Result};
-use core::borrow::Borrow;
 use core::char;
 use indoc::indoc;
 use std::{
@@ -16,12 +32,60 @@ use std::{
 };
 
 pub const USAGE: &str = indoc! {"
-TODO: add usage
-
+USAGE:
+    expert_system [OPTIONS] <FILE>
+    expert_system [OPTIONS] <COMMAND> <ARGS>
+
+COMMANDS:
+    solve <FILE>              Answer the queries of FILE, the default command
+    check <FILE>              Check the rules and facts of FILE for contradictions and mistakes
+    table <RULE>              Print the truth table of RULE as markdown, csv or text
+    explain <FILE> <QUERY>    Show why QUERY has its value
+    repl [FILE]               Start an interactive session, optionally loading FILE
+    fmt <FILE>                Print FILE with its rules and comments aligned
+    normalize <RULE>          Print RULE in negation, conjunctive and disjunctive normal form
+
+OPTIONS:
+    -f, --format <FORMAT>     Format of the query results: text, compact, json [default: text]
+                              or of a table: markdown, csv, text [default: markdown]
+    -m, --mode <MODE>         Inference engine: backward, forward, sat [default: backward]
+    -e, --explain             Show why each query has its value
+        --left-to-right       Group operators from left to right instead of by precedence
+        --check               With fmt, fail when FILE is not formatted instead of printing it
+        --deny-warnings       Fail when FILE has warnings, such as rules that always hold
+        --satisfying          With table, only print the rows in which RULE holds
+        --form <FORM>         With normalize, only print one form: nnf, cnf, dnf
+        --minimize            With normalize, merge the clauses and terms of CNF and DNF
+                              of a rule with at most 12 identifiers
+        --max-variables <N>   Identifiers allowed in a single rule, at most 30 [default: 24]
+        --max-rows <N>        Rows allowed in all truth tables together [default: 67108864]
+        --max-depth <N>       Nested identifiers the backward resolver may resolve [default: 1000]
+        --timeout <MS>        Milliseconds a truth table or a scenario may take
+    -v, --verbose             Also print the parsed input and rule map
+    -q, --quiet               Only report errors
+    -h, --help                Print this help
+    -V, --version             Print the version
+
+Use - as FILE to read from the standard input.
+Exits with 1 on any error and 2 when the rules and facts contradict each other.
 "};
 
-pub fn is_identifier(c: impl Borrow<char>) -> bool {
-    ('A'..='Z').contains(c.borrow())
+// Exit code of the binary when the rules and facts contradict each other
+pub const CONTRADICTION_EXIT_CODE: i32 = 2;
+
+// Identifiers start with a letter or underscore, followed by any number of letters, digits,
+// underscores and dots, e.g. `A`, `HasFever`, `engine_overheat` or `sensor.3.ok`.
+pub fn is_identifier(ident: &str) -> bool {
+    let mut chars = ident.chars();
+    chars.next().is_some_and(is_identifier_start) && chars.all(is_identifier_char)
+}
+
+pub fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+pub fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
 }
 
 pub fn read_file<T: FromStr>(file: &impl AsRef<Path>) -> Result<Vec<T>> {
@@ -50,9 +114,26 @@ mod tests_is_identifier {
     use pretty_assertions::assert_eq;
 
     #[test]
+    #[allow(clippy::manual_is_ascii_check)]
     fn all() {
         for c in '\0'..=char::MAX {
-            assert_eq!(is_identifier(&c), ('A'..='Z').contains(&c));
+            let start = ('A'..='Z').contains(&c) || ('a'..='z').contains(&c) || c == '_';
+            assert_eq!(is_identifier_start(c), start);
+            assert_eq!(
+                is_identifier_char(c),
+                start || ('0'..='9').contains(&c) || c == '.'
+            );
+            assert_eq!(is_identifier(&c.to_string()), start);
+        }
+    }
+
+    #[test]
+    fn names() {
+        for ident in ["A", "HasFever", "engine_overheat", "sensor.3.ok", "_x1"] {
+            assert!(is_identifier(ident), "{}", ident);
+        }
+        for ident in ["", "1A", ".A", "A-B", "A B", "Ä"] {
+            assert!(!is_identifier(ident), "{}", ident);
         }
     }
 }
@@ -68,7 +149,7 @@ mod tests_read_file {
     fn empty() -> Result<()> {
         let input_file = test_utils::input_file_path("read_file/empty.txt");
         let result: Vec<i32> = read_file(&input_file)?;
-        assert_eq!(result, vec![]);
+        assert_eq!(result, Vec::<i32>::new());
         Ok(())
     }
 
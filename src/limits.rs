@@ -0,0 +1,72 @@
+use crate::*;
+use error::{ExpertSystemError, Limit};
+
+use std::time::{Duration, Instant};
+
+// Default number of identifiers in a single rule
+pub const MAX_RULE_VARIABLES: usize = 24;
+
+// Number of identifiers a rule can never exceed whatever its limits, the truth table of such a
+// rule already has 2^30 rows and each row is still an index on every platform
+pub const RULE_VARIABLES_CAP: usize = 30;
+
+// Number of identifiers of a rule whose normal forms can be minimised, finding a cover of
+// the prime implicants takes time in the number of rows times the number of primes
+pub const MAX_MINIMIZE_VARIABLES: usize = 12;
+
+// Default number of rows of all truth tables of a RuleMap together
+pub const MAX_TABLE_ROWS: usize = 1 << 26;
+
+// Default number of identifiers the backward resolver can be resolving at the same time
+pub const MAX_DEPTH: usize = 1000;
+
+// Bounds on the work done for an input, every truth table doubles in size with each
+// identifier of its rule and the resolver recurses once per identifier of a chain of rules.
+// Exceeding any of them is reported as ExpertSystemError::LimitExceeded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Limits {
+    // Number of identifiers in a single rule, never more than RULE_VARIABLES_CAP
+    pub rule_variables: usize,
+    // Number of rows of all truth tables together
+    pub table_rows: usize,
+    // Number of identifiers being resolved at the same time
+    pub depth: usize,
+    // Time that building a truth table or resolving the queries of a scenario may take,
+    // unbounded when None
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            rule_variables: MAX_RULE_VARIABLES,
+            table_rows: MAX_TABLE_ROWS,
+            depth: MAX_DEPTH,
+            timeout: None,
+        }
+    }
+}
+
+impl Limits {
+    // Returns an error when `actual` is more than the `max` allowed for `limit`
+    pub(crate) fn check(limit: Limit, max: usize, actual: usize) -> Result<(), ExpertSystemError> {
+        match actual > max {
+            true => Err(ExpertSystemError::LimitExceeded { limit, max, actual }),
+            false => Ok(()),
+        }
+    }
+
+    // Returns an error when more time than the timeout has passed since `start`
+    pub(crate) fn check_timeout(&self, start: Instant) -> Result<(), ExpertSystemError> {
+        let elapsed = start.elapsed();
+        match self.timeout {
+            Some(timeout) if elapsed >= timeout => Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::Timeout,
+                max: timeout.as_millis() as usize,
+                actual: elapsed.as_millis() as usize,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
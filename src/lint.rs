@@ -0,0 +1,231 @@
+use crate::*;
+use diagnostics::Location;
+use input::Input;
+use parser::{RuleMap, TruthTable};
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// Mistake in an input that does not stop it from being solved. Warnings about a rule are
+// located at the line of the rule when the input knows it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub location: Option<Location>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarningKind {
+    // The rule holds for every assignment, so it has no effect
+    Tautology(String),
+    // The rule holds for no assignment, so it contradicts any facts
+    Unsatisfiable(String),
+    // The rule has the same truth table as `original`, which is on `line` when known
+    DuplicateRule {
+        rule: String,
+        original: String,
+        line: Option<usize>,
+    },
+    // The query is about an identifier that appears in no rule
+    UnknownQuery(String),
+    // The fact is about an identifier that appears in no rule
+    UnusedFact(String),
+}
+
+// Checks the rules and scenarios of `input`, whose rules make up `map`
+pub fn lint(input: &Input, map: &RuleMap) -> Vec<Warning> {
+    let tables: HashMap<&str, &TruthTable> = map
+        .truth_tables()
+        .into_iter()
+        .map(|t| (t.rule.as_str(), t.as_ref()))
+        .collect();
+    // Rules that are not in the map are parsed again
+    let rules: Vec<Option<Cow<TruthTable>>> = input
+        .rules
+        .iter()
+        .map(|rule| match tables.get(rule.as_str()) {
+            Some(table) => Some(Cow::Borrowed(*table)),
+            None => TruthTable::parse(rule, map.grammar()).ok().map(Cow::Owned),
+        })
+        .collect();
+    let mut warnings = vec![];
+    let mut seen: HashMap<(&[String], &[bool]), usize> = HashMap::new();
+    for (index, (rule, table)) in input.rules.iter().zip(rules.iter()).enumerate() {
+        let table = match table {
+            Some(table) => table,
+            None => continue,
+        };
+        let location = input.locations.get(index);
+        let kind = if table.results.iter().all(|r| *r) {
+            Some(WarningKind::Tautology(rule.clone()))
+        } else if table.results.iter().all(|r| !r) {
+            Some(WarningKind::Unsatisfiable(rule.clone()))
+        } else {
+            match seen.get(&(&table.variables[..], &table.results[..])) {
+                Some(first) => Some(WarningKind::DuplicateRule {
+                    rule: rule.clone(),
+                    original: input.rules[*first].clone(),
+                    line: input.locations.get(*first).map(|l| l.line),
+                }),
+                None => {
+                    seen.insert((&table.variables, &table.results), index);
+                    None
+                }
+            }
+        };
+        if let Some(kind) = kind {
+            warnings.push(Warning {
+                kind,
+                location: location.cloned(),
+            });
+        }
+    }
+
+    let identifiers: HashSet<&String> = map.identifiers().collect();
+    let mut unknown = |idents: Vec<&String>, kind: fn(String) -> WarningKind| {
+        let mut reported = HashSet::new();
+        for ident in idents {
+            if !identifiers.contains(ident) && reported.insert(ident) {
+                warnings.push(Warning {
+                    kind: kind(ident.clone()),
+                    location: None,
+                });
+            }
+        }
+    };
+    let scenarios = input.scenarios.iter();
+    unknown(
+        scenarios.clone().flat_map(|s| s.queries.iter()).collect(),
+        WarningKind::UnknownQuery,
+    );
+    unknown(
+        scenarios.flat_map(|s| s.facts.iter()).collect(),
+        WarningKind::UnusedFact,
+    );
+    warnings
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WarningKind::Tautology(rule) => write!(f, "Rule '{}' always holds", rule),
+            WarningKind::Unsatisfiable(rule) => write!(f, "Rule '{}' never holds", rule),
+            WarningKind::DuplicateRule {
+                rule,
+                original,
+                line,
+            } => {
+                write!(f, "Rule '{}' is the same as '{}'", rule, original)?;
+                match line {
+                    Some(line) => write!(f, " on line {}", line),
+                    None => Ok(()),
+                }
+            }
+            WarningKind::UnknownQuery(ident) => {
+                write!(f, "Query '{}' does not appear in any rule", ident)
+            }
+            WarningKind::UnusedFact(ident) => {
+                write!(f, "Fact '{}' does not appear in any rule", ident)
+            }
+        }
+    }
+}
+
+// Warnings with a location are rendered with the line they refer to, see Location
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        match &self.location {
+            Some(location) => {
+                writeln!(f)?;
+                location.fmt_snippet(f)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_lint {
+    use super::*;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    fn lint_lines(lines: &[&str]) -> Result<Vec<WarningKind>> {
+        let input = Input::from_lines(lines, "<input>")?;
+        let map = RuleMap::try_from(input.rules.clone())?;
+        Ok(lint(&input, &map).into_iter().map(|w| w.kind).collect())
+    }
+
+    #[test]
+    fn clean() -> Result<()> {
+        assert_eq!(lint_lines(&["A + B => C", "C => D", "=AB", "?D"])?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn rules() -> Result<()> {
+        assert_eq!(
+            lint_lines(&["A => A | B", "A => !A", "1 => 0", "=A", "?B"])?,
+            vec![
+                WarningKind::Tautology("A=>A|B".to_string()),
+                WarningKind::Unsatisfiable("1=>0".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn duplicates() -> Result<()> {
+        assert_eq!(
+            lint_lines(&["A + B => C", "B + A => C", "A + B => C", "=A", "?C"])?,
+            vec![
+                WarningKind::DuplicateRule {
+                    rule: "B+A=>C".to_string(),
+                    original: "A+B=>C".to_string(),
+                    line: Some(1),
+                },
+                WarningKind::DuplicateRule {
+                    rule: "A+B=>C".to_string(),
+                    original: "A+B=>C".to_string(),
+                    line: Some(1),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn identifiers() -> Result<()> {
+        assert_eq!(
+            lint_lines(&["A => B", "=AX", "?BY", "=X", "?Y"])?,
+            vec![
+                WarningKind::UnknownQuery("Y".to_string()),
+                WarningKind::UnusedFact("X".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn display() -> Result<()> {
+        let lines = ["A => B", "", "  B => A | 1  # always", "=A", "?B"];
+        let input = Input::from_lines(&lines, "rules.txt")?;
+        let map = RuleMap::try_from(input.rules.clone())?;
+        let result: Vec<String> = lint(&input, &map).iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            result,
+            vec![indoc! {"
+                Rule 'B=>A|1' always holds
+                 --> rules.txt:3:3
+                  |
+                3 |   B => A | 1  # always
+                  |   ^^^^^^^^^^
+            "}
+            .to_string()]
+        );
+        Ok(())
+    }
+}
@@ -1,29 +1,334 @@
 extern crate expert_system;
+use cli::{Cli, Command, Format, Mode, Verbosity};
+use diagnostics::Location;
+use error::ExpertSystemError;
 use expert_system::*;
-use input::Input;
-use parser::RuleMap;
-
-use anyhow::{Context, Result};
-use std::{env, path::PathBuf};
-
-fn handle_cli() -> String {
-    let args: Vec<String> = env::args().collect();
-    match args.len() {
-        2 => args[1].clone(),
-        _ => {
-            eprint!("{}", USAGE);
+use forward::ForwardChainer;
+use input::{format_identifiers, Input, Scenario};
+use json::ToJson;
+use lint::Warning;
+use normal::{format_expr, normal_form, NormalForm};
+use parser::{RuleMap, Truth, TruthTable};
+use repl::Repl;
+use report::{Report, ScenarioReport};
+use resolver::Resolver;
+use sat_resolver::SatResolver;
+use table::TableFormat;
+
+use anyhow::{anyhow, Context, Result};
+use std::{
+    env, fs,
+    io::{self, BufRead, Read},
+    path::{Path, PathBuf},
+};
+
+fn handle_cli() -> Cli {
+    match Cli::parse(env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprint!("Error: {:#}\n\n{}", err, USAGE);
             std::process::exit(1);
         }
     }
 }
 
-fn main() -> Result<()> {
-    let input_file = handle_cli();
-    let input = Input::try_from(PathBuf::from(input_file)).context("Unable to read input file")?;
+fn read_input(file: &Path) -> Result<Input> {
+    if file == Path::new("-") {
+        let lines = io::stdin()
+            .lock()
+            .lines()
+            .collect::<Result<Vec<String>, _>>()
+            .context("Failed to read standard input")?;
+        Ok(Input::from_lines(&lines, "<stdin>")?)
+    } else {
+        Ok(Input::try_from(PathBuf::from(file))?)
+    }
+}
+
+fn load(file: &Path, cli: &Cli) -> Result<(Input, RuleMap)> {
+    let input = read_input(file).context("Unable to read input file")?;
+    let mut map = RuleMap::with_limits(cli.grammar, cli.limits);
+    map.insert_vec(input.rules.clone())
+        .context("Failed to parse rule")?;
+    if cli.verbosity == Verbosity::Verbose {
+        println!("{:?}", input);
+        println!("{:?}", map);
+    }
+    Ok((input, map))
+}
+
+// Context of a contradiction in the scenario at `index`, which only needs its number when
+// there are multiple scenarios
+fn inconsistent(input: &Input, index: usize) -> String {
+    match input.scenarios.len() {
+        1 => "Inconsistent rules and facts".to_string(),
+        _ => format!("Inconsistent rules and facts in scenario {}", index + 1),
+    }
+}
+
+fn solve_scenario(
+    cli: &Cli,
+    map: &RuleMap,
+    scenario: &Scenario,
+    report: &mut ScenarioReport,
+) -> Result<()> {
+    match cli.mode {
+        Mode::Backward => {
+            let mut resolver = Resolver::new(map, &scenario.facts);
+            resolver.check()?;
+            for query in scenario.queries.iter() {
+                let value = resolver.resolve(query)?;
+                let explanation = match cli.explain {
+                    true => Some(resolver.explain(query)?),
+                    false => None,
+                };
+                report.push(query, value, explanation);
+            }
+        }
+        Mode::Forward => {
+            let mut chainer = ForwardChainer::new(map, &scenario.facts);
+            chainer.run()?;
+            for query in scenario.queries.iter() {
+                report.push(query, chainer.value(query), None);
+            }
+            report.derived = Some(chainer.derived().into_iter().map(String::from).collect());
+        }
+        Mode::Sat => {
+            let mut resolver = SatResolver::new(map, &scenario.facts);
+            resolver.check()?;
+            for query in scenario.queries.iter() {
+                report.push(query, resolver.resolve(query)?, None);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_scenario(cli: &Cli, report: &ScenarioReport) {
+    let explanations = report.queries.iter().filter_map(|q| q.explanation.as_ref());
+    match cli.format {
+        Format::Text if cli.explain => {
+            for explanation in explanations {
+                print!("{}", explanation);
+            }
+        }
+        Format::Text => {
+            for result in report.queries.iter() {
+                println!("{} is {}", result.query, result.value);
+            }
+            match &report.derived {
+                Some(derived) if derived.is_empty() => println!("Nothing derived"),
+                Some(derived) => println!("Derived: {}", derived.join(", ")),
+                None => {}
+            }
+        }
+        Format::Compact => {
+            let results: Vec<String> = report
+                .queries
+                .iter()
+                .map(|result| {
+                    let value = match result.value {
+                        Truth::True => '1',
+                        Truth::False => '0',
+                        Truth::Undetermined => '?',
+                    };
+                    format!("{}={}", result.query, value)
+                })
+                .collect();
+            println!("{}", results.join(" "));
+            for explanation in explanations {
+                print!("{}", explanation);
+            }
+        }
+        Format::Json => unreachable!("JSON is printed for the whole report"),
+        Format::Markdown | Format::Csv => {
+            unreachable!("only tables are printed as markdown or csv")
+        }
+    }
+}
+
+// Prints the warnings to the standard error, unless they are only part of the JSON output.
+// Fails when there are any and they are denied.
+fn warn(cli: &Cli, warnings: &[Warning]) -> Result<()> {
+    let denied = cli.deny_warnings && !warnings.is_empty();
+    if cli.verbosity > Verbosity::Quiet && (cli.format != Format::Json || denied) {
+        for warning in warnings {
+            eprintln!("Warning: {}", warning.to_string().trim_end());
+        }
+    }
+    match (denied, warnings.len()) {
+        (false, _) => Ok(()),
+        (true, 1) => Err(anyhow!("Found 1 warning with --deny-warnings")),
+        (true, n) => Err(anyhow!("Found {} warnings with --deny-warnings", n)),
+    }
+}
+
+fn solve(cli: &Cli, file: &Path) -> Result<()> {
+    let (input, map) = load(file, cli)?;
+    let mut report = Report::new(&input);
+    report.warnings = lint::lint(&input, &map);
+    warn(cli, &report.warnings)?;
+    let scenarios = input.scenarios.iter().zip(report.scenarios.iter_mut());
+    for (index, (scenario, result)) in scenarios.enumerate() {
+        solve_scenario(cli, &map, scenario, result).with_context(|| inconsistent(&input, index))?;
+    }
+    if cli.verbosity == Verbosity::Quiet {
+        return Ok(());
+    }
+    if cli.format == Format::Json {
+        println!("{:#}", report.to_json());
+        return Ok(());
+    }
+    // Results are grouped under the facts of their scenario when there are several
+    for (index, result) in report.scenarios.iter().enumerate() {
+        if report.scenarios.len() > 1 && cli.format == Format::Text {
+            println!(
+                "Scenario {}: ={}",
+                index + 1,
+                format_identifiers(&result.facts)
+            );
+        }
+        print_scenario(cli, result);
+    }
+    Ok(())
+}
+
+fn check(cli: &Cli, file: &Path) -> Result<()> {
+    let (input, map) = load(file, cli)?;
+    warn(cli, &lint::lint(&input, &map))?;
+    for (index, scenario) in input.scenarios.iter().enumerate() {
+        let facts = &scenario.facts;
+        match cli.mode {
+            Mode::Backward => Resolver::new(&map, facts).check(),
+            Mode::Forward => ForwardChainer::new(&map, facts).run().map(|_| ()),
+            Mode::Sat => SatResolver::new(&map, facts).check(),
+        }
+        .with_context(|| inconsistent(&input, index))?;
+    }
+    if cli.verbosity > Verbosity::Quiet {
+        println!("No contradictions found");
+    }
+    Ok(())
+}
+
+// Parses a rule given on the command line, errors point into the rule itself
+fn parse_rule(cli: &Cli, rule: &str) -> Result<TruthTable> {
+    TruthTable::parse_with_limits(rule, cli.grammar, &cli.limits)
+        .map_err(|err| err.locate(|span| Location::new("<rule>", 1, rule, span.start..span.end)))
+        .context(format!("Failed to create truth table from: '{}'", rule))
+}
 
-    println!("{:?}", input);
-    let map = RuleMap::try_from(input.rules).context("Failed to parse rule")?;
-    println!("{:?}", map);
+fn table(cli: &Cli, rule: &str) -> Result<()> {
+    let table = parse_rule(cli, rule)?;
+    let format = match cli.format {
+        Format::Csv => TableFormat::Csv,
+        Format::Text => TableFormat::Text,
+        _ => TableFormat::Markdown,
+    };
+    print!("{}", table.render(format, cli.satisfying));
+    Ok(())
+}
 
+fn normalize(cli: &Cli, rule: &str) -> Result<()> {
+    let table = parse_rule(cli, rule)?;
+    let format = |form| -> Result<String> {
+        let expr = normal_form(&table, form, cli.minimize, &cli.limits)?;
+        Ok(format_expr(&expr, &table.variables, cli.grammar))
+    };
+    match cli.form {
+        Some(form) => println!("{}", format(form)?),
+        None => {
+            // Every form is converted before printing, so a failure prints none of them
+            let (nnf, cnf, dnf) = (
+                format(NormalForm::Nnf)?,
+                format(NormalForm::Cnf)?,
+                format(NormalForm::Dnf)?,
+            );
+            println!("NNF: {}", nnf);
+            println!("CNF: {}", cnf);
+            println!("DNF: {}", dnf);
+        }
+    }
     Ok(())
 }
+
+fn explain(cli: &Cli, file: &Path, query: &str) -> Result<()> {
+    let (input, map) = load(file, cli)?;
+    for (index, scenario) in input.scenarios.iter().enumerate() {
+        if input.scenarios.len() > 1 {
+            println!(
+                "Scenario {}: ={}",
+                index + 1,
+                format_identifiers(&scenario.facts)
+            );
+        }
+        let mut resolver = Resolver::new(&map, &scenario.facts);
+        print!("{}", resolver.explain(query)?);
+    }
+    Ok(())
+}
+
+fn fmt(file: &Path, check: bool) -> Result<()> {
+    let (name, content) = if file == Path::new("-") {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .context("Failed to read standard input")?;
+        ("<stdin>".to_string(), content)
+    } else {
+        let content = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read '{}'", file.display()))?;
+        (file.display().to_string(), content)
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let formatted = formatter::format_lines(&lines, &name)?;
+    if !check {
+        print!("{}", formatted);
+    } else if formatted != content {
+        return Err(anyhow!("'{}' is not formatted", name));
+    }
+    Ok(())
+}
+
+fn repl(cli: &Cli, file: Option<&Path>) -> Result<()> {
+    let mut repl = Repl::with_limits(cli.grammar, cli.limits);
+    if let Some(file) = file {
+        repl.load(file)?;
+    }
+    repl.run(io::stdin().lock(), io::stdout())
+}
+
+fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Command::Solve(ref file) => solve(&cli, file),
+        Command::Check(ref file) => check(&cli, file),
+        Command::Table(ref rule) => table(&cli, rule),
+        Command::Explain(ref file, ref query) => explain(&cli, file, query),
+        Command::Repl(ref file) => repl(&cli, file.as_deref()),
+        Command::Fmt(ref file) => fmt(file, cli.check),
+        Command::Normalize(ref rule) => normalize(&cli, rule),
+        Command::Help => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        Command::Version => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    let cli = handle_cli();
+    if let Err(err) = run(cli) {
+        let error = err.downcast_ref::<ExpertSystemError>();
+        match error {
+            Some(error) if error.location().is_some() => eprint!("Error: {}", error),
+            _ => eprintln!("Error: {:#}", err),
+        }
+        std::process::exit(match error {
+            Some(ExpertSystemError::Contradiction(_)) => CONTRADICTION_EXIT_CODE,
+            _ => 1,
+        });
+    }
+}
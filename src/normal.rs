@@ -0,0 +1,447 @@
+use crate::*;
+use error::{ExpertSystemError, Limit};
+use expr::Expr;
+use limits::{Limits, MAX_MINIMIZE_VARIABLES};
+use parser::{Grammar, TruthTable};
+
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+// Normal form a rule can be converted to. `Nnf` rewrites the rule itself into only `!`, `+`
+// and `|`, with `!` applied to identifiers only. `Cnf` and `Dnf` are read from the truth
+// table of the rule: a clause per row in which it does not hold, or a term per row in which
+// it holds, unless they are minimised.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NormalForm {
+    Nnf,
+    Cnf,
+    Dnf,
+}
+
+// Converts the rule of `table` to `form`, see NormalForm. When `minimize` is set clauses and
+// terms are merged using Quine-McCluskey, which keeps the result equivalent to the rule.
+// NNF is not minimised. The result refers to the variables of `table`.
+//
+// Minimising fails for rules with more than MAX_MINIMIZE_VARIABLES identifiers and once the
+// timeout of `limits` has passed.
+pub fn normal_form(
+    table: &TruthTable,
+    form: NormalForm,
+    minimize: bool,
+    limits: &Limits,
+) -> Result<Expr, ExpertSystemError> {
+    let minimize = match minimize && form != NormalForm::Nnf {
+        true => {
+            let len = table.variables.len();
+            Limits::check(Limit::MinimizeVariables, MAX_MINIMIZE_VARIABLES, len)?;
+            Some((limits, Instant::now()))
+        }
+        false => None,
+    };
+    Ok(match form {
+        NormalForm::Nnf => nnf(&table.expr),
+        NormalForm::Cnf => cnf(table, minimize)?,
+        NormalForm::Dnf => dnf(table, minimize)?,
+    })
+}
+
+// Limits of minimising a normal form and the time it started at, None to not minimise
+type Minimize<'a> = Option<(&'a Limits, Instant)>;
+
+pub fn nnf(expr: &Expr) -> Expr {
+    negation_normal_form(expr, false)
+}
+
+// Conjunction of clauses that is false exactly in the rows in which the rule does not hold
+fn cnf(table: &TruthTable, minimize: Minimize) -> Result<Expr, ExpertSystemError> {
+    let len = table.variables.len();
+    let rows = rows(table, false);
+    let clauses = implicants(&rows, len, minimize)?
+        .into_iter()
+        .map(|(value, mask)| {
+            // A clause is false in a row when all of its literals are, so they are the negation
+            // of the values in that row
+            let literals = literals(value, mask, len).map(|(i, value)| literal(i, !value));
+            fold(literals, Expr::Or, Expr::Bool(false))
+        });
+    Ok(fold(clauses, Expr::And, Expr::Bool(true)))
+}
+
+// Disjunction of terms that is true exactly in the rows in which the rule holds
+fn dnf(table: &TruthTable, minimize: Minimize) -> Result<Expr, ExpertSystemError> {
+    let len = table.variables.len();
+    let rows = rows(table, true);
+    let terms = implicants(&rows, len, minimize)?
+        .into_iter()
+        .map(|(value, mask)| {
+            let literals = literals(value, mask, len).map(|(i, value)| literal(i, value));
+            fold(literals, Expr::And, Expr::Bool(true))
+        });
+    Ok(fold(terms, Expr::Or, Expr::Bool(false)))
+}
+
+// Writes `expr` as the text of a rule, with the identifiers of `variables` and only the
+// parentheses that `grammar` requires to read it back as the same expression
+pub fn format_expr(expr: &Expr, variables: &[String], grammar: Grammar) -> String {
+    let operand = |e: &Expr, min: u8| match precedence(e, grammar) < min {
+        true => format!("({})", format_expr(e, variables, grammar)),
+        false => format_expr(e, variables, grammar),
+    };
+    let binary = |l: &Expr, op: &str, r: &Expr, expr: &Expr| {
+        // `+`, `|` and `^` can be chained, a right operand only needs its parentheses when it
+        // has another operator of the same precedence. The implicators are never chained.
+        let p = precedence(expr, grammar);
+        let chained = matches!(expr, Expr::And(..) | Expr::Or(..) | Expr::Xor(..));
+        let same = std::mem::discriminant(r) == std::mem::discriminant(expr);
+        let (left, right) = match chained {
+            true => (p, if same { p } else { p + 1 }),
+            false => (p + 1, p + 1),
+        };
+        format!("{} {} {}", operand(l, left), op, operand(r, right))
+    };
+    match expr {
+        Expr::Bool(b) => if *b { "1" } else { "0" }.to_string(),
+        Expr::Var(i) => variables[*i].clone(),
+        Expr::Not(e) => format!("!{}", operand(e, precedence(expr, grammar))),
+        Expr::And(l, r) => binary(l, "+", r, expr),
+        Expr::Or(l, r) => binary(l, "|", r, expr),
+        Expr::Xor(l, r) => binary(l, "^", r, expr),
+        Expr::Imply(l, r) => binary(l, "=>", r, expr),
+        Expr::Iff(l, r) => binary(l, "<=>", r, expr),
+    }
+}
+
+// How strongly an operator binds its operands in `grammar`, see parser::Grammar
+fn precedence(expr: &Expr, grammar: Grammar) -> u8 {
+    match expr {
+        Expr::And(..) | Expr::Or(..) | Expr::Xor(..) if grammar == Grammar::LeftToRight => 2,
+        Expr::Iff(..) => 0,
+        Expr::Imply(..) => 1,
+        Expr::Xor(..) => 2,
+        Expr::Or(..) => 3,
+        Expr::And(..) => 4,
+        Expr::Not(_) => 5,
+        Expr::Bool(_) | Expr::Var(_) => 6,
+    }
+}
+
+// Pushes the negations down to the identifiers, `negated` is whether `expr` is under an odd
+// number of them. Constants are folded into the operators around them, so the result is
+// either a constant or has none.
+fn negation_normal_form(expr: &Expr, negated: bool) -> Expr {
+    let nnf = negation_normal_form;
+    match (expr, negated) {
+        (Expr::Bool(b), _) => Expr::Bool(b ^ negated),
+        (Expr::Var(i), false) => Expr::Var(*i),
+        (Expr::Var(i), true) => Expr::Not(Box::new(Expr::Var(*i))),
+        (Expr::Not(e), _) => nnf(e, !negated),
+        (Expr::And(l, r), false) => and(nnf(l, false), nnf(r, false)),
+        (Expr::And(l, r), true) => or(nnf(l, true), nnf(r, true)),
+        (Expr::Or(l, r), false) => or(nnf(l, false), nnf(r, false)),
+        (Expr::Or(l, r), true) => and(nnf(l, true), nnf(r, true)),
+        (Expr::Imply(l, r), false) => or(nnf(l, true), nnf(r, false)),
+        (Expr::Imply(l, r), true) => and(nnf(l, false), nnf(r, true)),
+        // Both sides are equal, or with the negation or a xor they differ
+        (Expr::Iff(l, r), false) | (Expr::Xor(l, r), true) => or(
+            and(nnf(l, false), nnf(r, false)),
+            and(nnf(l, true), nnf(r, true)),
+        ),
+        (Expr::Iff(l, r), true) | (Expr::Xor(l, r), false) => or(
+            and(nnf(l, false), nnf(r, true)),
+            and(nnf(l, true), nnf(r, false)),
+        ),
+    }
+}
+
+// Conjunction of `l` and `r`, without the constants in it
+fn and(l: Expr, r: Expr) -> Expr {
+    match (l, r) {
+        (Expr::Bool(false), _) | (_, Expr::Bool(false)) => Expr::Bool(false),
+        (Expr::Bool(true), e) | (e, Expr::Bool(true)) => e,
+        (l, r) => Expr::And(Box::new(l), Box::new(r)),
+    }
+}
+
+// Disjunction of `l` and `r`, without the constants in it
+fn or(l: Expr, r: Expr) -> Expr {
+    match (l, r) {
+        (Expr::Bool(true), _) | (_, Expr::Bool(true)) => Expr::Bool(true),
+        (Expr::Bool(false), e) | (e, Expr::Bool(false)) => e,
+        (l, r) => Expr::Or(Box::new(l), Box::new(r)),
+    }
+}
+
+// Returns the rows of `table` with `result`
+fn rows(table: &TruthTable, result: bool) -> Vec<usize> {
+    (0..table.results.len())
+        .filter(|row| table.results[*row] == result)
+        .collect()
+}
+
+// An implicant is a set of rows given as `(value, mask)`, it contains every row that equals
+// `value` in the bits that are not set in `mask`
+type Implicant = (usize, usize);
+
+// Returns implicants that together contain exactly `rows`. Without `minimize` every row is
+// an implicant on its own, otherwise the prime implicants of the rows are combined until
+// they are all covered, starting with the essential ones.
+fn implicants(
+    rows: &[usize],
+    len: usize,
+    minimize: Minimize,
+) -> Result<Vec<Implicant>, ExpertSystemError> {
+    let (limits, start) = match minimize {
+        Some(minimize) => minimize,
+        None => return Ok(rows.iter().map(|row| (*row, 0)).collect()),
+    };
+    let primes = prime_implicants(rows, len, limits, start)?;
+    let contains = |(value, mask): Implicant, row: usize| row & !mask == value;
+    let mut uncovered: BTreeSet<usize> = rows.iter().copied().collect();
+    let mut cover = Vec::new();
+    while let Some(&first) = uncovered.first() {
+        // An essential implicant is the only one containing some row, otherwise the one
+        // containing the most uncovered rows is taken, preferring fewer literals
+        let mut essential = None;
+        for row in uncovered.iter() {
+            limits.check_timeout(start)?;
+            let mut containing = primes.iter().filter(|p| contains(**p, *row));
+            if let (Some(p), None) = (containing.next(), containing.next()) {
+                essential = Some(*p);
+                break;
+            }
+        }
+        let implicant = essential.unwrap_or_else(|| {
+            let covers = |p: &Implicant| uncovered.iter().filter(|r| contains(*p, **r)).count();
+            *primes
+                .iter()
+                .filter(|p| contains(**p, first))
+                .max_by_key(|p| (covers(p), p.1.count_ones(), std::cmp::Reverse(**p)))
+                .unwrap()
+        });
+        uncovered.retain(|row| !contains(implicant, *row));
+        cover.push(implicant);
+    }
+    // Ordered by their literals, variables that are not fixed come last
+    cover.sort_unstable_by_key(|(value, mask)| {
+        (0..len)
+            .map(|i| 1 << (len - 1 - i))
+            .map(|bit| (mask & bit != 0, value & bit != 0))
+            .collect::<Vec<_>>()
+    });
+    Ok(cover)
+}
+
+// Merges implicants that differ in a single bit until none can be merged, the implicants
+// that were never merged are the prime implicants
+fn prime_implicants(
+    rows: &[usize],
+    len: usize,
+    limits: &Limits,
+    start: Instant,
+) -> Result<Vec<Implicant>, ExpertSystemError> {
+    let mut primes = BTreeSet::new();
+    let mut current: BTreeSet<Implicant> = rows.iter().map(|row| (*row, 0)).collect();
+    while !current.is_empty() {
+        let mut merged = BTreeSet::new();
+        let mut next = BTreeSet::new();
+        for &(value, mask) in current.iter() {
+            limits.check_timeout(start)?;
+            for bit in (0..len).map(|b| 1 << b).filter(|bit| mask & bit == 0) {
+                let other = (value ^ bit, mask);
+                if current.contains(&other) {
+                    next.insert((value & !bit, mask | bit));
+                    merged.insert((value, mask));
+                    merged.insert(other);
+                }
+            }
+        }
+        primes.extend(current.difference(&merged).copied());
+        current = next;
+    }
+    Ok(primes.into_iter().collect())
+}
+
+// Returns the variables that `value` fixes outside of `mask`, with their value
+fn literals(value: usize, mask: usize, len: usize) -> impl Iterator<Item = (usize, bool)> {
+    (0..len)
+        .map(move |i| (i, 1 << (len - 1 - i)))
+        .filter(move |(_, bit)| mask & bit == 0)
+        .map(move |(i, bit)| (i, value & bit != 0))
+}
+
+fn literal(index: usize, value: bool) -> Expr {
+    match value {
+        true => Expr::Var(index),
+        false => Expr::Not(Box::new(Expr::Var(index))),
+    }
+}
+
+// Combines `exprs` with `op` from the left, `empty` when there are none
+fn fold(
+    exprs: impl Iterator<Item = Expr>,
+    op: fn(Box<Expr>, Box<Expr>) -> Expr,
+    empty: Expr,
+) -> Expr {
+    exprs
+        .reduce(|l, r| op(Box::new(l), Box::new(r)))
+        .unwrap_or(empty)
+}
+
+#[cfg(test)]
+mod tests_normal {
+    use super::*;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn convert(rule: &str, form: NormalForm, minimize: bool) -> Result<String> {
+        let table = TruthTable::try_from(rule)?;
+        let expr = normal_form(&table, form, minimize, &Limits::default())?;
+        let len = table.variables.len();
+        for row in 0..table.results.len() {
+            assert_eq!(expr.evaluate(row, len), table.results[row], "row {}", row);
+        }
+        Ok(format_expr(&expr, &table.variables, Grammar::default()))
+    }
+
+    #[test]
+    fn nnf() -> Result<()> {
+        assert_eq!(
+            convert("A | B => X | V", NormalForm::Nnf, false)?,
+            "!A + !B | X | V"
+        );
+        assert_eq!(
+            convert("!(A + !B) => C", NormalForm::Nnf, false)?,
+            "A + !B | C"
+        );
+        assert_eq!(
+            convert("A ^ B <=> C", NormalForm::Nnf, false)?,
+            "(A + !B | !A + B) + C | (A + B | !A + !B) + !C"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cnf() -> Result<()> {
+        assert_eq!(
+            convert("A | B => C", NormalForm::Cnf, false)?,
+            "(A | !B | C) + (!A | B | C) + (!A | !B | C)"
+        );
+        assert_eq!(
+            convert("A | B => X | V", NormalForm::Cnf, true)?,
+            "(!A | V | X) + (!B | V | X)"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn dnf() -> Result<()> {
+        assert_eq!(
+            convert("A + B => C", NormalForm::Dnf, false)?,
+            "!A + !B + !C | !A + !B + C | !A + B + !C | !A + B + C | A + !B + !C \
+             | A + !B + C | A + B + C"
+        );
+        assert_eq!(convert("A + B => C", NormalForm::Dnf, true)?, "!A | !B | C");
+        assert_eq!(
+            convert("A <=> B", NormalForm::Dnf, true)?,
+            "!A + !B | A + B"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn constant() -> Result<()> {
+        assert_eq!(convert("A => A", NormalForm::Cnf, false)?, "1");
+        assert_eq!(convert("A => A", NormalForm::Dnf, true)?, "1");
+        assert_eq!(convert("1 => 0", NormalForm::Dnf, true)?, "0");
+        assert_eq!(convert("1 => 0", NormalForm::Cnf, false)?, "0");
+        assert_eq!(convert("1 => 0", NormalForm::Nnf, false)?, "0");
+        assert_eq!(convert("A + 1 => 0 | B", NormalForm::Nnf, false)?, "!A | B");
+        assert_eq!(convert("A | !0 => B", NormalForm::Nnf, false)?, "B");
+        assert_eq!(
+            convert("A ^ 0 <=> A", NormalForm::Nnf, false)?,
+            "A + A | !A + !A"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn minimize() -> Result<()> {
+        // The prime implicant !B + !C is left out, its rows are covered by the others
+        let rule = "A + B | !A + C => D";
+        let canonical = convert(rule, NormalForm::Dnf, false)?;
+        let minimized = convert(rule, NormalForm::Dnf, true)?;
+        assert_eq!(minimized, "!A + !C | A + !B | D");
+        assert!(minimized.len() < canonical.len());
+        Ok(())
+    }
+
+    #[test]
+    fn error_minimize_variables() -> Result<()> {
+        let rule = "A ^ B ^ C ^ D ^ E ^ F ^ G ^ H ^ I ^ J ^ K ^ L => M";
+        let table = TruthTable::try_from(rule)?;
+        let limits = Limits::default();
+        assert!(normal_form(&table, NormalForm::Dnf, false, &limits).is_ok());
+        assert!(normal_form(&table, NormalForm::Nnf, true, &limits).is_ok());
+        let result = normal_form(&table, NormalForm::Dnf, true, &limits);
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::MinimizeVariables,
+                max: 12,
+                actual: 13,
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn error_timeout() -> Result<()> {
+        let table = TruthTable::try_from("A ^ B ^ C => D")?;
+        let limits = Limits {
+            timeout: Some(Duration::ZERO),
+            ..Limits::default()
+        };
+        assert!(normal_form(&table, NormalForm::Cnf, false, &limits).is_ok());
+        let result = normal_form(&table, NormalForm::Cnf, true, &limits);
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::Timeout,
+                max: 0,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn format() -> Result<()> {
+        let table = TruthTable::try_from("(A | B) + !(C ^ D) <=> E ^ (F | G) + H")?;
+        assert_eq!(
+            format_expr(&table.expr, &table.variables, Grammar::Precedence),
+            "(A | B) + !(C ^ D) <=> E ^ (F | G) + H"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn format_left_to_right() -> Result<()> {
+        // Binary operators are grouped in the order they appear, only a right operand with
+        // another operator needs parentheses
+        let rule = "A | B + C + (D ^ E) => !(F | G) + H";
+        let table = TruthTable::parse(rule, Grammar::LeftToRight)?;
+        let result = format_expr(&table.expr, &table.variables, Grammar::LeftToRight);
+        assert_eq!(result, rule);
+        let parsed = TruthTable::parse(&result, Grammar::LeftToRight)?;
+        assert_eq!(parsed.expr, table.expr);
+
+        // Read with precedence, the same expression needs more parentheses
+        let result = format_expr(&table.expr, &table.variables, Grammar::Precedence);
+        assert_eq!(result, "(A | B) + C + (D ^ E) => !(F | G) + H");
+        let parsed = TruthTable::parse(&result, Grammar::Precedence)?;
+        assert_eq!(parsed.expr, table.expr);
+        Ok(())
+    }
+}
@@ -1,242 +1,662 @@
 use crate::*;
-use permutation_iter::PermutationIter;
+use error::{ExpertSystemError, LexError, Limit, ParseError};
+use expr::Expr;
+use limits::{Limits, RULE_VARIABLES_CAP};
+use table::TableFormat;
 
-use anyhow::{anyhow, Context, Result};
 use std::borrow::Borrow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::iter::Peekable;
-use std::rc::Rc;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
 use Token::*;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Direction {
+    #[default]
     UniDirectional,
     BiDirectional,
 }
 
-#[derive(Debug, Copy, Clone)]
+// Value of an identifier, Undetermined when the rules allow it to be both true and false.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Truth {
+    True,
+    False,
+    Undetermined,
+}
+
+impl From<bool> for Truth {
+    fn from(b: bool) -> Self {
+        match b {
+            true => Truth::True,
+            false => Truth::False,
+        }
+    }
+}
+
+impl fmt::Display for Truth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Truth::True => write!(f, "true"),
+            Truth::False => write!(f, "false"),
+            Truth::Undetermined => write!(f, "undetermined"),
+        }
+    }
+}
+
+// Grammar decides how operators without parentheses are grouped. `Precedence` binds `!`
+// tighter than `+`, `+` tighter than `|` and `|` tighter than `^`. `LeftToRight` groups the
+// binary operators in the order they appear, which is how older versions read rules.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Grammar {
+    #[default]
+    Precedence,
+    LeftToRight,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Token {
     Implicator(Direction),
     Operator(char),
     Parenthesis(char),
     Bool(bool),
+    Identifier(String),
+}
+
+// Range of characters `start..end` in the text a rule was parsed from
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+}
+
+// Number of rows of a truth table that are evaluated between checks of the timeout
+const ROWS_PER_TIMEOUT_CHECK: usize = 1 << 12;
+
+// Rule is a parsed rule. Its expression is always an Imply or Iff at the top and refers to
+// the identifiers by their index in the sorted `variables`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rule {
+    pub variables: Vec<String>,
+    pub expr: Expr,
 }
 
 #[derive(Default)]
-pub struct RuleParser;
+pub struct RuleParser {
+    variables: Vec<String>,
+    grammar: Grammar,
+    rule: String,
+    len: usize,
+}
 
 impl<'a> RuleParser {
     pub fn new() -> Self {
-        RuleParser {}
+        RuleParser::default()
     }
 
-    fn get_direction<I>(&mut self, lexer: &mut I, c: char) -> Result<Direction>
+    pub fn with_grammar(grammar: Grammar) -> Self {
+        RuleParser {
+            grammar,
+            ..RuleParser::default()
+        }
+    }
+
+    fn lex_error(&self, error: LexError, span: Span) -> ExpertSystemError {
+        ExpertSystemError::Lex {
+            error,
+            rule: self.rule.clone(),
+            span,
+            location: None,
+        }
+    }
+
+    fn parse_error(&self, error: ParseError, span: Span) -> ExpertSystemError {
+        ExpertSystemError::Parse {
+            error,
+            rule: self.rule.clone(),
+            span,
+            location: None,
+        }
+    }
+
+    fn get_direction<I>(
+        &mut self,
+        lexer: &mut I,
+        start: usize,
+        c: char,
+    ) -> Result<Direction, ExpertSystemError>
     where
-        I: Iterator<Item = char>,
+        I: Iterator<Item = (usize, char)>,
     {
-        if let Some(next) = lexer.next() {
-            match (c, next) {
-                ('=', '>') => Ok(Direction::UniDirectional),
-                ('<', '=') => match lexer.next() {
-                    Some('>') => Ok(Direction::BiDirectional),
-                    _ => Err(anyhow!("Unable to finish lexing implicator")),
-                },
-                _ => Err(anyhow!("Unable to finish lexing implicator")),
-            }
-        } else {
-            Err(anyhow!("Unable to finish lexing implicator"))
-        }
-    }
-
-    pub fn tokenize(&mut self, input: &str) -> Result<Vec<Token>> {
-        let mut lexer = input.chars();
-        let mut token_list: Vec<Token> = Vec::new();
-        while let Some(c) = lexer.next() {
-            match c {
-                '(' | ')' => token_list.push(Parenthesis(c)),
-                '!' | '+' | '|' | '^' => token_list.push(Operator(c)),
-                '=' | '<' => token_list.push(Implicator(self.get_direction(&mut lexer, c)?)),
-                '0' => token_list.push(Bool(false)),
-                '1' => token_list.push(Bool(true)),
-                c if c.is_whitespace() => {}
-                _ => return Err(anyhow!("Unexpected character: {}", c)),
+        let (direction, rest) = match c {
+            '=' => (Direction::UniDirectional, &['>'][..]),
+            _ => (Direction::BiDirectional, &['=', '>'][..]),
+        };
+        let mut end = start + 1;
+        for expected in rest {
+            match lexer.next() {
+                Some((i, next)) if next == *expected => end = i + 1,
+                next => {
+                    let end = next.map_or(end, |(i, _)| i + 1);
+                    return Err(
+                        self.lex_error(LexError::IncompleteImplicator, Span::new(start, end))
+                    );
+                }
             }
         }
+        Ok(direction)
+    }
+
+    pub fn tokenize(&mut self, input: &str) -> Result<Vec<(Token, Span)>, ExpertSystemError> {
+        self.rule = input.to_string();
+        self.len = input.chars().count();
+        let len = self.len;
+        let mut lexer = input.chars().enumerate().peekable();
+        let mut token_list: Vec<(Token, Span)> = Vec::new();
+        while let Some((start, c)) = lexer.next() {
+            let token = match c {
+                '(' | ')' => Parenthesis(c),
+                '!' | '+' | '|' | '^' => Operator(c),
+                '=' | '<' => Implicator(self.get_direction(&mut lexer, start, c)?),
+                '0' => Bool(false),
+                '1' => Bool(true),
+                c if is_identifier_start(c) => {
+                    let mut ident = c.to_string();
+                    while let Some((_, c)) = lexer.next_if(|(_, c)| is_identifier_char(*c)) {
+                        ident.push(c);
+                    }
+                    Identifier(ident)
+                }
+                c if c.is_whitespace() => continue,
+                _ => {
+                    return Err(self.lex_error(
+                        LexError::UnexpectedCharacter(c),
+                        Span::new(start, start + 1),
+                    ))
+                }
+            };
+            let end = lexer.peek().map_or(len, |(i, _)| *i);
+            token_list.push((token, Span::new(start, end)));
+        }
         Ok(token_list)
     }
 
-    fn get_rule<I>(&mut self, token_list: &mut Peekable<I>) -> Result<bool>
+    // Span of the position right after the last token
+    fn end(&self) -> Span {
+        Span::new(self.len, self.len + 1)
+    }
+
+    fn get_rule<I>(&mut self, token_list: &mut Peekable<I>) -> Result<Expr, ExpertSystemError>
     where
-        I: Iterator<Item = &'a Token>,
+        I: Iterator<Item = &'a (Token, Span)>,
     {
-        let antecedent = self.get_operator(token_list)?;
-        if let Some(implicator) = token_list.next() {
-            let consequent = self.get_operator(token_list)?;
-            match implicator {
-                Implicator(direction) => match direction {
-                    Direction::UniDirectional => Ok(!antecedent | consequent),
-                    Direction::BiDirectional => Ok(antecedent == consequent),
-                },
-                _ => unreachable!(),
+        let antecedent = Box::new(self.get_operator(token_list, 0)?);
+        match token_list.next() {
+            Some((Implicator(direction), _)) => {
+                let consequent = Box::new(self.get_operator(token_list, 0)?);
+                match direction {
+                    Direction::UniDirectional => Ok(Expr::Imply(antecedent, consequent)),
+                    Direction::BiDirectional => Ok(Expr::Iff(antecedent, consequent)),
+                }
             }
-        } else {
-            Err(anyhow!("No implicator found"))
+            Some((t, span)) => Err(self.parse_error(ParseError::UnexpectedToken(t.clone()), *span)),
+            None => Err(self.parse_error(ParseError::MissingImplicator, self.end())),
+        }
+    }
+
+    // Returns how strongly a binary operator binds its operands
+    fn binding_power(&self, operator: char, span: Span) -> Result<u8, ExpertSystemError> {
+        match (self.grammar, operator) {
+            (_, '!') => {
+                Err(self.parse_error(ParseError::InvalidOperator(Operator(operator)), span))
+            }
+            (Grammar::LeftToRight, _) => Ok(1),
+            (Grammar::Precedence, '^') => Ok(1),
+            (Grammar::Precedence, '|') => Ok(2),
+            (Grammar::Precedence, _) => Ok(3),
         }
     }
 
-    fn get_operator<I>(&mut self, token_list: &mut Peekable<I>) -> Result<bool>
+    // Parses binary operators using precedence climbing, only operators that bind at least
+    // as strong as `min_power` are part of the returned expression.
+    fn get_operator<I>(
+        &mut self,
+        token_list: &mut Peekable<I>,
+        min_power: u8,
+    ) -> Result<Expr, ExpertSystemError>
     where
-        I: Iterator<Item = &'a Token>,
+        I: Iterator<Item = &'a (Token, Span)>,
     {
-        let mut node = self.get_factor(token_list);
-        while let Some(Operator(_)) = token_list.peek() {
-            node = match token_list.next() {
-                Some(Operator('+')) => Ok(node? & self.get_factor(token_list)?),
-                Some(Operator('|')) => Ok(node? | self.get_factor(token_list)?),
-                Some(Operator('^')) => Ok(node? ^ self.get_factor(token_list)?),
-                _ => unreachable!(),
+        let mut node = self.get_factor(token_list)?;
+        while let Some((Operator(operator), span)) = token_list.peek() {
+            let operator = *operator;
+            let power = self.binding_power(operator, *span)?;
+            if power < min_power {
+                break;
+            }
+            token_list.next();
+            let lhs = Box::new(node);
+            let rhs = Box::new(self.get_operator(token_list, power + 1)?);
+            node = match operator {
+                '+' => Expr::And(lhs, rhs),
+                '|' => Expr::Or(lhs, rhs),
+                _ => Expr::Xor(lhs, rhs),
             }
         }
-        node
+        Ok(node)
     }
 
-    fn get_factor<I>(&mut self, token_list: &mut Peekable<I>) -> Result<bool>
+    fn get_factor<I>(&mut self, token_list: &mut Peekable<I>) -> Result<Expr, ExpertSystemError>
     where
-        I: Iterator<Item = &'a Token>,
+        I: Iterator<Item = &'a (Token, Span)>,
     {
         match token_list.next() {
-            Some(Parenthesis('(')) => {
-                let res = self.get_operator(token_list);
+            Some((Parenthesis('('), span)) => {
+                let res = self.get_operator(token_list, 0);
                 match token_list.next() {
-                    Some(Parenthesis(')')) => res,
-                    _ => Err(anyhow!("Missing closing parenthesis")),
+                    Some((Parenthesis(')'), _)) => res,
+                    _ => Err(self.parse_error(ParseError::MissingClosingParenthesis, *span)),
                 }
             }
-            Some(Operator('!')) => Ok(!self.get_factor(token_list)?),
-            Some(Bool(b)) => Ok(*b),
-            Some(t) => Err(anyhow!("Invalid factor token '{:?}'", t)),
-            None => Err(anyhow!("Unexpected end of token list")),
+            Some((Operator('!'), _)) => Ok(Expr::Not(Box::new(self.get_factor(token_list)?))),
+            Some((Bool(b), _)) => Ok(Expr::Bool(*b)),
+            Some((Identifier(ident), span)) => match self.variables.binary_search(ident) {
+                Ok(index) => Ok(Expr::Var(index)),
+                Err(_) => {
+                    Err(self.parse_error(ParseError::UnknownIdentifier(ident.clone()), *span))
+                }
+            },
+            Some((t, span)) => Err(self.parse_error(ParseError::InvalidFactor(t.clone()), *span)),
+            None => Err(self.parse_error(ParseError::UnexpectedEnd, self.end())),
+        }
+    }
+
+    // Parses a rule once into an expression that can be evaluated for every row of its
+    // truth table. Errors in the syntax of the rule are a Lex or Parse error.
+    pub fn parse(&mut self, input: &str) -> Result<Rule, ExpertSystemError> {
+        let token_list = self.tokenize(input)?;
+        self.variables = token_list
+            .iter()
+            .filter_map(|(token, _)| match token {
+                Identifier(ident) => Some(ident.clone()),
+                _ => None,
+            })
+            .collect();
+        self.variables.sort_unstable();
+        self.variables.dedup();
+
+        let mut token_list = token_list.iter().peekable();
+        let expr = self.get_rule(&mut token_list)?;
+        if let Some((t, span)) = token_list.next() {
+            return Err(self.parse_error(ParseError::UnexpectedToken(t.clone()), *span));
         }
+        Ok(Rule {
+            variables: std::mem::take(&mut self.variables),
+            expr,
+        })
     }
 
-    pub fn evaluate(&mut self, input: &str) -> Result<bool> {
-        let token_list = self
-            .tokenize(input)
-            .context(format!("Failed to tokenize input: '{}'", input))?;
-        self.get_rule(&mut token_list.iter().peekable())
+    // Evaluates a rule that contains no identifiers, e.g. `1 + 0 => 0`
+    pub fn evaluate(&mut self, input: &str) -> Result<bool, ExpertSystemError> {
+        let rule = self.parse(input)?;
+        match rule.variables.first() {
+            Some(ident) => Err(self.parse_error(
+                ParseError::UnassignedIdentifier(ident.clone()),
+                Span::new(0, self.len),
+            )),
+            None => Ok(rule.expr.evaluate(0, 0)),
+        }
     }
 }
 
 // TruthTable struct holds the truth table data of an input rule.
-// It can be constructed using a PermutationIter. Since the permutations generated by
-// PermuationIter is always guaranteed to follow the same pattern, the order of the results
-// implicitly holds the propositional data for each entry in the TruthTable. Example:
+// It is constructed by parsing the rule once and evaluating its expression for every
+// assignment of the sorted variables. The index of a result is the assignment it belongs
+// to, with the first variable as the most significant bit. Example for `A => B`:
 // `0 => 0` implies index 0b00, results[0]
 // `0 => 1` implies index 0b01, results[1]
 // `1 => 0` implies index 0b10, results[2]
 // `1 => 1` implies index 0b11, results[3]
+// Next to the results the table remembers the rule it was built from, the direction of the
+// implicator and which identifiers appear on either side of it, this decides which
+// identifiers can be derived from the rule.
 #[derive(Default, Eq, PartialEq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TruthTable {
-    pub variables: Vec<char>,
+    pub rule: String,
+    pub variables: Vec<String>,
     pub results: Vec<bool>,
+    pub direction: Direction,
+    pub antecedent: Vec<String>,
+    pub consequent: Vec<String>,
+    // Parsed rule, its variables are the indices into `variables`
+    pub expr: Expr,
 }
 
 impl TruthTable {
     pub fn new() -> Self {
         TruthTable {
+            rule: String::new(),
             variables: Vec::new(),
             results: Vec::new(),
+            direction: Direction::UniDirectional,
+            antecedent: Vec::new(),
+            consequent: Vec::new(),
+            expr: Expr::default(),
         }
     }
+
+    // Returns the value of the variable at `index` in the permutation of `row`
+    pub fn value(&self, row: usize, index: usize) -> bool {
+        row & (1 << (self.variables.len() - 1 - index)) != 0
+    }
+
+    // Returns the identifiers from which `ident` can be derived using this table, None if
+    // the table can not be used to derive `ident`. A uni-directional rule only derives the
+    // identifiers in its consequent.
+    pub fn sources(&self, ident: &str) -> Option<&[String]> {
+        let contains = |side: &[String]| side.iter().any(|v| v == ident);
+        if contains(&self.consequent) {
+            Some(&self.antecedent)
+        } else if self.direction == Direction::BiDirectional && contains(&self.antecedent) {
+            Some(&self.consequent)
+        } else {
+            None
+        }
+    }
+
+    // Parses `formula` with `grammar` and evaluates it for every assignment of its variables
+    pub fn parse(formula: &str, grammar: Grammar) -> Result<Self, ExpertSystemError> {
+        TruthTable::parse_with_limits(formula, grammar, &Limits::default())
+    }
+
+    // Same as `parse`, but fails when the table would have more identifiers or rows than
+    // `limits` allow
+    pub fn parse_with_limits(
+        formula: &str,
+        grammar: Grammar,
+        limits: &Limits,
+    ) -> Result<Self, ExpertSystemError> {
+        let rule = RuleParser::with_grammar(grammar).parse(formula)?;
+        TruthTable::from_expr(formula.to_string(), rule.variables, rule.expr, limits)
+    }
+
+    // Evaluates `expr`, an implication whose variables index into `variables`, for every
+    // assignment of its variables
+    fn from_expr(
+        rule: String,
+        variables: Vec<String>,
+        expr: Expr,
+        limits: &Limits,
+    ) -> Result<Self, ExpertSystemError> {
+        let start = Instant::now();
+        let len = variables.len();
+        let max = limits.rule_variables.min(RULE_VARIABLES_CAP);
+        Limits::check(Limit::RuleVariables, max, len)?;
+        Limits::check(Limit::TableRows, limits.table_rows, 1 << len)?;
+        let identifiers = |side: &Expr| -> Vec<String> {
+            side.variables()
+                .into_iter()
+                .map(|i| variables[i].clone())
+                .collect()
+        };
+        let (direction, antecedent, consequent) = match &expr {
+            Expr::Imply(lhs, rhs) => (
+                Direction::UniDirectional,
+                identifiers(lhs),
+                identifiers(rhs),
+            ),
+            Expr::Iff(lhs, rhs) => (Direction::BiDirectional, identifiers(lhs), identifiers(rhs)),
+            _ => unreachable!("a parsed rule always has an implicator"),
+        };
+        let mut results = Vec::with_capacity(1 << len);
+        for row in 0..1 << len {
+            if row % ROWS_PER_TIMEOUT_CHECK == ROWS_PER_TIMEOUT_CHECK - 1 {
+                limits.check_timeout(start)?;
+            }
+            results.push(expr.evaluate(row, len));
+        }
+        Ok(TruthTable {
+            rule,
+            variables,
+            results,
+            direction,
+            antecedent,
+            consequent,
+            expr,
+        })
+    }
 }
 
-impl TryFrom<PermutationIter> for TruthTable {
-    type Error = anyhow::Error;
+impl TryFrom<&str> for TruthTable {
+    type Error = ExpertSystemError;
 
-    fn try_from(mut permutation_iter: PermutationIter) -> Result<Self, Self::Error> {
-        let mut table = Self::new();
-        let mut parser = RuleParser::new();
-        for permutation in permutation_iter.by_ref() {
-            table.results.push(
-                parser
-                    .evaluate(&permutation)
-                    .context(format!("Failed to evaluate permutation {}", permutation))?,
-            );
-        }
-        table.variables.append(&mut permutation_iter.variables);
-        Ok(table)
+    fn try_from(formula: &str) -> Result<Self, Self::Error> {
+        TruthTable::parse(formula, Grammar::default())
     }
 }
 
 impl fmt::Debug for TruthTable {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let len = self.variables.len();
-        for v in &self.variables {
-            write!(f, "| {} ", v)?;
-        }
-        writeln!(f, "| = |")?;
-        writeln!(f, "{}|", "|---".repeat(len + 1))?;
-        for (i, result) in self.results.iter().enumerate() {
-            for b in 0..len {
-                write!(
-                    f,
-                    "| {} ",
-                    if i & (1 << (len - 1 - b)) == 0 { 0 } else { 1 }
-                )?
-            }
-            writeln!(f, "| {} |", if *result { 1 } else { 0 })?;
-        }
-        Ok(())
+        write!(f, "{}", self.render(TableFormat::Markdown, false))
+    }
+}
+
+// Identifies a rule of a RuleMap. Ids are handed out in the order rules are inserted and are
+// not reused after their rule is removed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuleId(usize);
+
+impl fmt::Display for RuleId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
 
-// Structure that holds key pairs of identifier and all related truth tables.
+// Reads an id as written by Display, it only names a rule of the map that handed it out
+impl FromStr for RuleId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(RuleId)
+    }
+}
+
+// Structure that holds key pairs of identifier and all related truth tables. The tables
+// are shared through an Arc, so a loaded map is Send and Sync and can be queried by many
+// threads at once, each with its own Resolver and facts.
+//
+// Every inserted rule is also kept under its RuleId. Rules with the same truth table share
+// a single Arc, which stays under its variables until the last of these rules is removed.
 #[derive(Default)]
 pub struct RuleMap {
-    map: HashMap<char, HashSet<Rc<TruthTable>>>,
+    map: HashMap<String, HashSet<Arc<TruthTable>>>,
+    rules: BTreeMap<RuleId, Arc<TruthTable>>,
+    next_id: usize,
+    grammar: Grammar,
+    limits: Limits,
+    // Number of rows of all truth tables in the map
+    rows: usize,
 }
 
 impl RuleMap {
+    // Creates an empty map that parses the rules inserted into it using `grammar`
+    pub fn with_grammar(grammar: Grammar) -> Self {
+        RuleMap::with_limits(grammar, Limits::default())
+    }
+
+    // Creates an empty map that refuses rules exceeding `limits`, which also bound the
+    // resolvers working on the map
+    pub fn with_limits(grammar: Grammar, limits: Limits) -> Self {
+        RuleMap {
+            grammar,
+            limits,
+            ..RuleMap::default()
+        }
+    }
+
+    pub fn grammar(&self) -> Grammar {
+        self.grammar
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    // Returns all truth tables that contain `ident`
+    pub fn tables(&self, ident: &str) -> impl Iterator<Item = &Arc<TruthTable>> {
+        self.map.get(ident).into_iter().flatten()
+    }
+
+    // Returns all identifiers that appear in a rule, in no particular order
+    pub fn identifiers(&self) -> impl Iterator<Item = &String> {
+        self.map.keys()
+    }
+
+    // Returns whether `ident` appears in any rule
+    pub fn contains(&self, ident: &str) -> bool {
+        self.map.contains_key(ident)
+    }
+
+    // Returns every truth table in the map once, including those of rules without any
+    // identifier, which are not stored under any identifier
+    pub(crate) fn truth_tables(&self) -> HashSet<&Arc<TruthTable>> {
+        self.rules.values().collect()
+    }
+
+    // Returns every rule with its id, in the order they were inserted
+    pub fn rules(&self) -> impl Iterator<Item = (RuleId, &TruthTable)> {
+        self.rules.iter().map(|(id, table)| (*id, table.as_ref()))
+    }
+
+    // Returns the rule with `id`, None if it was removed
+    pub fn rule(&self, id: RuleId) -> Option<&TruthTable> {
+        self.rules.get(&id).map(|table| table.as_ref())
+    }
+
     // Inserts a new rule in the rulemap Ad-Hoc
-    pub fn insert<T>(&mut self, rule: T) -> Result<()>
+    pub fn insert<T>(&mut self, rule: T) -> Result<RuleId, ExpertSystemError>
     where
         T: Borrow<str>,
     {
-        let ptr = Rc::new(
-            TruthTable::try_from(PermutationIter::new(rule.borrow())).context(format!(
-                "Failed to create truth table from: '{}'",
-                rule.borrow()
-            ))?,
-        );
-        for v in ptr.variables.iter() {
-            let tables = self
-                .map
-                .entry(*v)
-                .or_insert_with(|| HashSet::from([Rc::clone(&ptr)]));
-            tables.insert(Rc::clone(&ptr));
-        }
-        Ok(())
+        let table = TruthTable::parse_with_limits(rule.borrow(), self.grammar, &self.limits)?;
+        let id = RuleId(self.next_id);
+        self.insert_table(id, Arc::new(table))?;
+        Ok(id)
     }
 
-    pub fn insert_vec<T>(&mut self, rules: Vec<T>) -> Result<()>
+    pub fn insert_vec<T>(&mut self, rules: Vec<T>) -> Result<Vec<RuleId>, ExpertSystemError>
     where
         T: Borrow<str>,
     {
-        for rule in rules.iter() {
-            self.insert(rule.borrow())?
+        rules
+            .iter()
+            .map(|rule| self.insert(rule.borrow()))
+            .collect()
+    }
+
+    // Removes the rule with `id` and every identifier that no other rule uses. Returns the
+    // truth table of the rule, None if there is no rule with `id`.
+    pub fn remove(&mut self, id: RuleId) -> Option<Arc<TruthTable>> {
+        let ptr = self.rules.remove(&id)?;
+        self.unlink(&ptr);
+        Some(ptr)
+    }
+
+    // Replaces the rule with `id` by `rule`, keeping its id. Returns the truth table of the
+    // replaced rule, None if there is no rule with `id`, in which case nothing is inserted.
+    // The map is unchanged when `rule` is invalid or exceeds the limits.
+    pub fn replace<T>(
+        &mut self,
+        id: RuleId,
+        rule: T,
+    ) -> Result<Option<Arc<TruthTable>>, ExpertSystemError>
+    where
+        T: Borrow<str>,
+    {
+        let table = TruthTable::parse_with_limits(rule.borrow(), self.grammar, &self.limits)?;
+        let old = match self.remove(id) {
+            Some(old) => old,
+            None => return Ok(None),
+        };
+        if let Err(err) = self.insert_table(id, Arc::new(table)) {
+            // The old table fit before the new one was tried, so it fits again
+            self.insert_table(id, Arc::clone(&old))?;
+            return Err(err);
         }
+        Ok(Some(old))
+    }
+
+    // Stores `ptr` as the rule with `id` and shares it between the entries of all its
+    // variables. An equal table that is already in the map is shared instead of `ptr`.
+    fn insert_table(&mut self, id: RuleId, ptr: Arc<TruthTable>) -> Result<(), ExpertSystemError> {
+        let shared = ptr
+            .variables
+            .first()
+            .and_then(|v| self.map.get(v))
+            .and_then(|tables| tables.get(&ptr));
+        let ptr = match shared {
+            Some(shared) => Arc::clone(shared),
+            None if ptr.variables.is_empty() => ptr,
+            None => {
+                let rows = self.rows + ptr.results.len();
+                Limits::check(Limit::TableRows, self.limits.table_rows, rows)?;
+                self.rows = rows;
+                for v in ptr.variables.iter() {
+                    let tables = self.map.entry(v.clone()).or_default();
+                    tables.insert(Arc::clone(&ptr));
+                }
+                ptr
+            }
+        };
+        self.rules.insert(id, ptr);
+        self.next_id = self.next_id.max(id.0 + 1);
         Ok(())
     }
+
+    // Removes `ptr` from the entries of its variables once no rule uses it anymore,
+    // dropping the variables that are left without any table
+    fn unlink(&mut self, ptr: &Arc<TruthTable>) {
+        if ptr.variables.is_empty() || self.rules.values().any(|t| Arc::ptr_eq(t, ptr)) {
+            return;
+        }
+        self.rows -= ptr.results.len();
+        for v in ptr.variables.iter() {
+            if let Some(tables) = self.map.get_mut(v) {
+                tables.remove(ptr);
+                if tables.is_empty() {
+                    self.map.remove(v);
+                }
+            }
+        }
+    }
 }
 
 impl<T> TryFrom<Vec<T>> for RuleMap
 where
     T: Borrow<str>,
 {
-    type Error = anyhow::Error;
+    type Error = ExpertSystemError;
 
-    fn try_from(rules: Vec<T>) -> Result<Self> {
+    fn try_from(rules: Vec<T>) -> Result<Self, Self::Error> {
         let mut map = RuleMap::default();
         map.insert_vec(rules)?;
         Ok(map)
@@ -261,6 +681,157 @@ impl fmt::Debug for RuleMap {
     }
 }
 
+// A RuleMap is written as its grammar, its limits and every rule with its id and truth
+// table, in the order of their ids. Reading it back keeps the ids and shares each table
+// between its variables again, as `insert` does.
+#[cfg(feature = "serde")]
+impl serde::Serialize for RuleMap {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(serde::Serialize)]
+        struct Rule<'a> {
+            id: RuleId,
+            table: &'a TruthTable,
+        }
+
+        #[derive(serde::Serialize)]
+        struct Rules<'a> {
+            grammar: Grammar,
+            limits: Limits,
+            rules: Vec<Rule<'a>>,
+        }
+
+        Rules {
+            grammar: self.grammar,
+            limits: self.limits,
+            rules: self.rules().map(|(id, table)| Rule { id, table }).collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RuleMap {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Rule {
+            id: RuleId,
+            table: TableFields,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Rules {
+            grammar: Grammar,
+            limits: Limits,
+            rules: Vec<Rule>,
+        }
+
+        // Every table is parsed again from its rule, so that the limits of the map hold for
+        // it and a table that does not match its rule is refused
+        let Rules {
+            grammar,
+            limits,
+            rules,
+        } = Rules::deserialize(deserializer)?;
+        let mut map = RuleMap::with_limits(grammar, limits);
+        for Rule { id, table: fields } in rules {
+            let table = TruthTable::parse_with_limits(&fields.rule, grammar, &limits)
+                .map_err(serde::de::Error::custom)?;
+            fields.check(&table).map_err(serde::de::Error::custom)?;
+            map.insert_table(id, Arc::new(table))
+                .map_err(serde::de::Error::custom)?;
+        }
+        Ok(map)
+    }
+}
+
+// Fields of a serialized TruthTable, which are only trusted once they match the table
+// evaluated from them
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TableFields {
+    rule: String,
+    variables: Vec<String>,
+    results: Vec<bool>,
+    direction: Direction,
+    antecedent: Vec<String>,
+    consequent: Vec<String>,
+    expr: Expr,
+}
+
+#[cfg(feature = "serde")]
+impl TableFields {
+    // Returns an error when the fields differ from `table`
+    fn check(&self, table: &TruthTable) -> Result<(), String> {
+        let matches = self.variables == table.variables
+            && self.results == table.results
+            && self.direction == table.direction
+            && self.antecedent == table.antecedent
+            && self.consequent == table.consequent
+            && self.expr == table.expr;
+        match matches {
+            true => Ok(()),
+            false => Err(format!(
+                "Truth table does not match its rule: '{}'",
+                self.rule
+            )),
+        }
+    }
+}
+
+// Without a grammar to parse its rule with, a table is evaluated again from its expression
+// within the default limits
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TruthTable {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let fields = TableFields::deserialize(deserializer)?;
+        if !matches!(fields.expr, Expr::Imply(..) | Expr::Iff(..)) {
+            return Err(D::Error::custom(format!(
+                "Truth table has no implicator: '{}'",
+                fields.rule
+            )));
+        }
+        if let Some(i) = fields.expr.variables().last() {
+            if *i >= fields.variables.len() {
+                return Err(D::Error::custom(format!(
+                    "Truth table has no variable {}: '{}'",
+                    i, fields.rule
+                )));
+            }
+        }
+        let table = TruthTable::from_expr(
+            fields.rule.clone(),
+            fields.variables.clone(),
+            fields.expr.clone(),
+            &Limits::default(),
+        )
+        .map_err(D::Error::custom)?;
+        fields.check(&table).map_err(D::Error::custom)?;
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests_truth {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn from_bool() {
+        assert_eq!(Truth::from(true), Truth::True);
+        assert_eq!(Truth::from(false), Truth::False);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Truth::True.to_string(), "true");
+        assert_eq!(Truth::False.to_string(), "false");
+        assert_eq!(Truth::Undetermined.to_string(), "undetermined");
+    }
+}
+
 #[cfg(test)]
 mod tests_rule_parser {
     use super::*;
@@ -347,14 +918,14 @@ mod tests_rule_parser {
     #[test]
     fn parenthesis() -> Result<()> {
         let mut parser = RuleParser::new();
-        assert_eq!(parser.evaluate("1 | 0 + 0 => 0")?, true);
+        assert_eq!(parser.evaluate("1 | 0 + 0 => 0")?, false);
         assert_eq!(parser.evaluate("(1 | 0) + 0 => 0")?, true);
         assert_eq!(parser.evaluate("1 | (0 + 0) => 0")?, false);
         assert_eq!(parser.evaluate("0 + 0 | 1 => 0")?, false);
         assert_eq!(parser.evaluate("(0 + 0) | 1 => 0")?, false);
         assert_eq!(parser.evaluate("0 + (0 | 1) => 0")?, true);
 
-        assert_eq!(parser.evaluate("1 => 1 | 0 + 0")?, false);
+        assert_eq!(parser.evaluate("1 => 1 | 0 + 0")?, true);
         assert_eq!(parser.evaluate("1 => (1 | 0) + 0")?, false);
         assert_eq!(parser.evaluate("1 => 1 | (0 + 0)")?, true);
         assert_eq!(parser.evaluate("1 => 0 + 0 | 1")?, true);
@@ -364,59 +935,181 @@ mod tests_rule_parser {
     }
 
     #[test]
-    fn error_empty() {
-        let result = RuleParser::new().evaluate("");
-        assert!(result.is_err());
+    fn precedence() -> Result<()> {
+        let mut parser = RuleParser::new();
+        assert_eq!(parser.evaluate("1 ^ 1 | 0 => 0")?, true);
+        assert_eq!(parser.evaluate("0 | 1 ^ 1 => 0")?, true);
+        assert_eq!(parser.evaluate("1 ^ 0 + 0 => 0")?, false);
+        assert_eq!(parser.evaluate("0 | 1 + !1 => 0")?, true);
+        assert_eq!(parser.evaluate("1 ^ 1 ^ 1 => 0")?, false);
+        Ok(())
+    }
+
+    #[test]
+    fn left_to_right() -> Result<()> {
+        let mut parser = RuleParser::with_grammar(Grammar::LeftToRight);
+        assert_eq!(parser.evaluate("1 | 0 + 0 => 0")?, true);
+        assert_eq!(parser.evaluate("0 + 0 | 1 => 0")?, false);
+        assert_eq!(parser.evaluate("1 => 1 | 0 + 0")?, false);
+        assert_eq!(parser.evaluate("1 ^ 1 | 0 => 0")?, true);
+        assert_eq!(parser.evaluate("0 | 1 ^ 1 => 0")?, true);
+        assert_eq!(parser.evaluate("1 ^ 1 | 1 => 0")?, false);
+        Ok(())
+    }
+
+    #[test]
+    fn parse() -> Result<()> {
+        let result = RuleParser::new().parse("C + !A <=> C | 1")?;
+        assert_eq!(result.variables, vec!["A", "C"]);
         assert_eq!(
-            result.unwrap_err().to_string(),
-            "Unexpected end of token list"
+            result.expr,
+            Expr::Iff(
+                Box::new(Expr::And(
+                    Box::new(Expr::Var(1)),
+                    Box::new(Expr::Not(Box::new(Expr::Var(0))))
+                )),
+                Box::new(Expr::Or(Box::new(Expr::Var(1)), Box::new(Expr::Bool(true)))),
+            )
         );
+        Ok(())
+    }
+
+    #[test]
+    fn long_identifiers() -> Result<()> {
+        let result = RuleParser::new().parse("HasFever + sensor.3.ok => engine_overheat")?;
+        assert_eq!(
+            result.variables,
+            vec!["HasFever", "engine_overheat", "sensor.3.ok"]
+        );
+        assert_eq!(
+            result.expr,
+            Expr::Imply(
+                Box::new(Expr::And(Box::new(Expr::Var(0)), Box::new(Expr::Var(2)))),
+                Box::new(Expr::Var(1)),
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn spans() -> Result<()> {
+        let result = RuleParser::new().tokenize("A <=> (Fever)")?;
+        let spans: Vec<Span> = result.iter().map(|(_, span)| *span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(0, 1),
+                Span::new(2, 5),
+                Span::new(6, 7),
+                Span::new(7, 12),
+                Span::new(12, 13)
+            ]
+        );
+        Ok(())
+    }
+
+    fn error_span(input: &str) -> Option<Span> {
+        match RuleParser::new().parse(input).err()? {
+            ExpertSystemError::Lex { span, .. } | ExpertSystemError::Parse { span, .. } => {
+                Some(span)
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_error(input: &str) -> Option<ParseError> {
+        match RuleParser::new().evaluate(input).err()? {
+            ExpertSystemError::Parse { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn error_spans() {
+        assert_eq!(error_span("A + $ => B"), Some(Span::new(4, 5)));
+        assert_eq!(error_span("A <= B"), Some(Span::new(2, 5)));
+        assert_eq!(error_span("A + => B"), Some(Span::new(4, 6)));
+        assert_eq!(error_span("A => B +"), Some(Span::new(8, 9)));
+        assert_eq!(error_span("A => B)"), Some(Span::new(6, 7)));
+        assert_eq!(error_span("A => (B"), Some(Span::new(5, 6)));
+    }
+
+    #[test]
+    fn error_rule() {
+        let result = RuleParser::new().parse("A + $ => B");
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::Lex { rule, location: None, .. }) if rule == "A + $ => B"
+        ));
+    }
+
+    #[test]
+    fn error_empty() {
+        assert_eq!(parse_error(""), Some(ParseError::UnexpectedEnd));
     }
 
     #[test]
     fn error_invalid_state() {
-        let result = RuleParser::new().evaluate("A => Z");
-        assert!(result.is_err());
         assert_eq!(
-            result.unwrap_err().to_string(),
-            "Failed to tokenize input: 'A => Z'"
+            parse_error("A => Z"),
+            Some(ParseError::UnassignedIdentifier("A".to_string()))
         );
     }
 
     #[test]
     fn error_invalid_operator() {
         let result = RuleParser::new().evaluate("0 = 1");
-        assert!(result.is_err());
-        assert_eq!(
-            result.unwrap_err().to_string(),
-            "Failed to tokenize input: '0 = 1'"
-        );
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::Lex {
+                error: LexError::IncompleteImplicator,
+                ..
+            })
+        ));
+
+        let result = RuleParser::new().evaluate("0 & 1 => 1");
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::Lex {
+                error: LexError::UnexpectedCharacter('&'),
+                ..
+            })
+        ));
     }
 
     #[test]
     fn error_missing_operator_half() {
-        let result = RuleParser::new().evaluate("0 | => 0");
-        assert!(result.is_err());
         assert_eq!(
-            result.unwrap_err().to_string(),
-            "Invalid factor token 'Implicator(UniDirectional)'"
+            parse_error("0 | => 0"),
+            Some(ParseError::InvalidFactor(Implicator(
+                Direction::UniDirectional
+            )))
         );
     }
 
     #[test]
     fn error_missing_implicator() {
-        let result = RuleParser::new().evaluate("0");
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err().to_string(), "No implicator found");
+        assert_eq!(parse_error("0"), Some(ParseError::MissingImplicator));
     }
 
     #[test]
-    fn error_missing_parenthesis() {
-        let result = RuleParser::new().evaluate("(0 => 0");
-        assert!(result.is_err());
+    fn error_trailing_token() {
+        let result = RuleParser::new().evaluate("0 => 0)");
         assert_eq!(
             result.unwrap_err().to_string(),
-            "Missing closing parenthesis"
+            "Unexpected token 'Parenthesis(')')'"
+        );
+        assert_eq!(
+            parse_error("0 => 0)"),
+            Some(ParseError::UnexpectedToken(Parenthesis(')')))
+        );
+    }
+
+    #[test]
+    fn error_missing_parenthesis() {
+        assert_eq!(
+            parse_error("(0 => 0"),
+            Some(ParseError::MissingClosingParenthesis)
         );
     }
 }
@@ -424,26 +1117,148 @@ mod tests_rule_parser {
 #[cfg(test)]
 mod tests_truth_table {
     use super::*;
+    use limits::MAX_RULE_VARIABLES;
 
     use anyhow::Result;
     use pretty_assertions::assert_eq;
+    use std::time::Duration;
 
     #[test]
     fn simple() -> Result<()> {
-        let result = TruthTable::try_from(PermutationIter::new("A => Z"))?;
-        assert_eq!(result.variables, vec!['A', 'Z']);
+        let result = TruthTable::try_from("A => Z")?;
+        assert_eq!(result.rule, "A => Z");
+        assert_eq!(result.variables, vec!["A", "Z"]);
         assert_eq!(result.results, vec![true, true, false, true]);
         Ok(())
     }
 
     #[test]
-    fn error_invalid_rule() {
-        let result = TruthTable::try_from(PermutationIter::new("A = Z"));
-        assert!(result.is_err());
+    fn sides() -> Result<()> {
+        let result = TruthTable::try_from("A + B => C | !B")?;
+        assert_eq!(result.direction, Direction::UniDirectional);
+        assert_eq!(result.antecedent, vec!["A", "B"]);
+        assert_eq!(result.consequent, vec!["B", "C"]);
+        Ok(())
+    }
+
+    #[test]
+    fn sources() -> Result<()> {
+        let result = TruthTable::try_from("A => B")?;
+        assert_eq!(result.sources("A"), None);
+        assert_eq!(result.sources("B"), Some(&["A".to_string()][..]));
+
+        let result = TruthTable::try_from("A <=> B")?;
+        assert_eq!(result.sources("A"), Some(&["B".to_string()][..]));
+        assert_eq!(result.sources("B"), Some(&["A".to_string()][..]));
+        Ok(())
+    }
+
+    #[test]
+    fn debug() -> Result<()> {
+        let result = TruthTable::try_from("Fever => A")?;
         assert_eq!(
-            result.unwrap_err().to_string(),
-            "Failed to evaluate permutation 0 = 0"
+            format!("{:?}", result),
+            indoc! {"
+                | A | Fever | = |
+                |---|-------|---|
+                | 0 | 0     | 1 |
+                | 0 | 1     | 0 |
+                | 1 | 0     | 1 |
+                | 1 | 1     | 1 |
+            "}
         );
+        Ok(())
+    }
+
+    #[test]
+    fn many_variables() -> Result<()> {
+        let result =
+            TruthTable::try_from("A + B + C + D + E + F + G + H => I | J | K | L | M | N | O | P")?;
+        assert_eq!(result.variables.len(), 16);
+        assert_eq!(result.results.len(), 1 << 16);
+        assert_eq!(result.results.iter().filter(|b| !**b).count(), 1);
+        assert!(!result.results[0b1111_1111_0000_0000]);
+        Ok(())
+    }
+
+    #[test]
+    fn error_invalid_rule() {
+        let result = TruthTable::try_from("A = Z");
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::Lex {
+                error: LexError::IncompleteImplicator,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn error_timeout() {
+        let limits = Limits {
+            timeout: Some(Duration::ZERO),
+            ..Limits::default()
+        };
+        let antecedent: Vec<String> = (0..12).map(|i| format!("V{}", i)).collect();
+        let rule = format!("{} => Z", antecedent.join(" + "));
+        let result = TruthTable::parse_with_limits(&rule, Grammar::default(), &limits);
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::Timeout,
+                ..
+            })
+        ));
+        // Small tables are built before the timeout is checked
+        assert!(TruthTable::parse_with_limits("A => B", Grammar::default(), &limits).is_ok());
+    }
+
+    #[test]
+    fn error_too_many_variables() {
+        let antecedent: Vec<String> = (0..MAX_RULE_VARIABLES).map(|i| format!("V{}", i)).collect();
+        let result = TruthTable::try_from(format!("{} => Z", antecedent.join(" + ")).as_str());
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::RuleVariables,
+                max: MAX_RULE_VARIABLES,
+                actual,
+            }) if actual == MAX_RULE_VARIABLES + 1
+        ));
+    }
+
+    #[test]
+    fn error_limits() {
+        let limits = Limits {
+            rule_variables: 2,
+            ..Limits::default()
+        };
+        let result = TruthTable::parse_with_limits("A + B => C", Grammar::default(), &limits);
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::RuleVariables,
+                max: 2,
+                actual: 3,
+            })
+        ));
+
+        let limits = Limits {
+            rule_variables: usize::MAX,
+            table_rows: usize::MAX,
+            ..Limits::default()
+        };
+        let antecedent: Vec<String> = (0..RULE_VARIABLES_CAP).map(|i| format!("V{}", i)).collect();
+        let rule = format!("{} => Z", antecedent.join(" + "));
+        let result = TruthTable::parse_with_limits(&rule, Grammar::default(), &limits);
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::RuleVariables,
+                max: RULE_VARIABLES_CAP,
+                actual,
+            }) if actual == RULE_VARIABLES_CAP + 1
+        ));
     }
 }
 
@@ -457,17 +1272,17 @@ mod tests_rule_map {
     #[test]
     fn empty() -> Result<()> {
         let result = RuleMap::try_from(Vec::<String>::new())?;
-        assert!(result.map.is_empty());
+        assert_eq!(result.identifiers().count(), 0);
         Ok(())
     }
 
     #[test]
     fn from() -> Result<()> {
         let result = RuleMap::try_from(vec!["A => B", "B => C"])?;
-        assert_eq!(result.map.len(), 3);
-        assert_eq!(result.map.get(&'A').map_or(0, |v| v.len()), 1);
-        assert_eq!(result.map.get(&'B').map_or(0, |v| v.len()), 2);
-        assert_eq!(result.map.get(&'C').map_or(0, |v| v.len()), 1);
+        assert_eq!(result.identifiers().count(), 3);
+        assert_eq!(result.tables("A").count(), 1);
+        assert_eq!(result.tables("B").count(), 2);
+        assert_eq!(result.tables("C").count(), 1);
         Ok(())
     }
 
@@ -475,15 +1290,15 @@ mod tests_rule_map {
     fn insert() -> Result<()> {
         let mut result = RuleMap::try_from(Vec::<String>::new())?;
         result.insert("A => B")?;
-        assert_eq!(result.map.len(), 2);
-        assert_eq!(result.map.get(&'A').map_or(0, |v| v.len()), 1);
-        assert_eq!(result.map.get(&'B').map_or(0, |v| v.len()), 1);
+        assert_eq!(result.identifiers().count(), 2);
+        assert_eq!(result.tables("A").count(), 1);
+        assert_eq!(result.tables("B").count(), 1);
 
         result.insert("B => C")?;
-        assert_eq!(result.map.len(), 3);
-        assert_eq!(result.map.get(&'A').map_or(0, |v| v.len()), 1);
-        assert_eq!(result.map.get(&'B').map_or(0, |v| v.len()), 2);
-        assert_eq!(result.map.get(&'C').map_or(0, |v| v.len()), 1);
+        assert_eq!(result.identifiers().count(), 3);
+        assert_eq!(result.tables("A").count(), 1);
+        assert_eq!(result.tables("B").count(), 2);
+        assert_eq!(result.tables("C").count(), 1);
         Ok(())
     }
 
@@ -491,20 +1306,326 @@ mod tests_rule_map {
     fn insert_vec() -> Result<()> {
         let mut result = RuleMap::try_from(Vec::<String>::new())?;
         result.insert_vec(vec!["A => B", "B => C"])?;
-        assert_eq!(result.map.len(), 3);
-        assert_eq!(result.map.get(&'A').map_or(0, |v| v.len()), 1);
-        assert_eq!(result.map.get(&'B').map_or(0, |v| v.len()), 2);
-        assert_eq!(result.map.get(&'C').map_or(0, |v| v.len()), 1);
+        assert_eq!(result.identifiers().count(), 3);
+        assert_eq!(result.tables("A").count(), 1);
+        assert_eq!(result.tables("B").count(), 2);
+        assert_eq!(result.tables("C").count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn grammar() -> Result<()> {
+        let mut result = RuleMap::with_grammar(Grammar::LeftToRight);
+        result.insert("A | B + C => D")?;
+        assert_eq!(result.tables("D").next().unwrap().results[0b1000], true);
+
+        let mut result = RuleMap::default();
+        result.insert("A | B + C => D")?;
+        assert_eq!(result.tables("D").next().unwrap().results[0b1000], false);
+        Ok(())
+    }
+
+    fn identifiers(map: &RuleMap) -> Vec<&str> {
+        let mut identifiers: Vec<&str> = map.identifiers().map(|i| i.as_str()).collect();
+        identifiers.sort_unstable();
+        identifiers
+    }
+
+    #[test]
+    fn rules() -> Result<()> {
+        let mut map = RuleMap::default();
+        let first = map.insert("A => B")?;
+        let ids = map.insert_vec(vec!["B => C", "1 => 0"])?;
+        assert!(first < ids[0] && ids[0] < ids[1]);
+        let rules: Vec<(RuleId, &str)> = map
+            .rules()
+            .map(|(id, table)| (id, table.rule.as_str()))
+            .collect();
+        assert_eq!(
+            rules,
+            vec![(first, "A => B"), (ids[0], "B => C"), (ids[1], "1 => 0")]
+        );
+        assert_eq!(map.rule(ids[0]).map(|t| t.rule.as_str()), Some("B => C"));
+        Ok(())
+    }
+
+    #[test]
+    fn remove() -> Result<()> {
+        let mut map = RuleMap::default();
+        let ids = map.insert_vec(vec!["A => B", "B => C"])?;
+        assert_eq!(
+            map.remove(ids[1]).map(|t| t.rule.clone()),
+            Some("B => C".to_string())
+        );
+        assert_eq!(identifiers(&map), vec!["A", "B"]);
+        assert_eq!(map.tables("B").count(), 1);
+        assert_eq!(map.rows, 4);
+        assert!(map.remove(ids[1]).is_none());
+        assert!(map.rule(ids[1]).is_none());
+
+        map.remove(ids[0]);
+        assert_eq!(map.identifiers().count(), 0);
+        assert_eq!(map.rows, 0);
+        assert_ne!(map.insert("A => B")?, ids[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_shared() -> Result<()> {
+        let mut map = RuleMap::default();
+        let ids = map.insert_vec(vec!["A => B", "A => B"])?;
+        assert_eq!(map.tables("A").count(), 1);
+        map.remove(ids[0]);
+        assert_eq!(map.tables("A").count(), 1);
+        assert_eq!(map.rules().count(), 1);
+        map.remove(ids[1]);
+        assert_eq!(map.identifiers().count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn replace() -> Result<()> {
+        let mut map = RuleMap::default();
+        let ids = map.insert_vec(vec!["A => B", "B => C"])?;
+        let old = map.replace(ids[1], "B => D")?;
+        assert_eq!(old.map(|t| t.rule.clone()), Some("B => C".to_string()));
+        assert_eq!(map.rule(ids[1]).map(|t| t.rule.as_str()), Some("B => D"));
+        assert_eq!(identifiers(&map), vec!["A", "B", "D"]);
+
+        let unknown = map.insert("X => Y")?;
+        map.remove(unknown);
+        assert!(map.replace(unknown, "X => Z")?.is_none());
+        assert_eq!(map.rules().count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn error_replace() -> Result<()> {
+        let limits = Limits {
+            table_rows: 8,
+            ..Limits::default()
+        };
+        let mut map = RuleMap::with_limits(Grammar::default(), limits);
+        let ids = map.insert_vec(vec!["A => B", "B => C"])?;
+        assert!(map.replace(ids[1], "B =>").is_err());
+        assert!(matches!(
+            map.replace(ids[1], "B + C => D"),
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::TableRows,
+                ..
+            })
+        ));
+        assert_eq!(map.rule(ids[1]).map(|t| t.rule.as_str()), Some("B => C"));
+        assert_eq!(identifiers(&map), vec!["A", "B", "C"]);
+        assert_eq!(map.rows, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn limits() -> Result<()> {
+        let limits = Limits {
+            table_rows: 12,
+            ..Limits::default()
+        };
+        let mut result = RuleMap::with_limits(Grammar::default(), limits);
+        result.insert_vec(vec!["A + B => C", "C => D"])?;
+        result.insert("C => D")?;
+        assert_eq!(result.rows, 12);
+        assert!(matches!(
+            result.insert("D => E"),
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::TableRows,
+                max: 12,
+                actual: 16,
+            })
+        ));
+        assert!(!result.contains("E"));
         Ok(())
     }
 
     #[test]
     fn error_invalid_rule() {
         let result = RuleMap::try_from(vec!["A =>"]);
-        assert!(result.is_err());
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::Parse {
+                error: ParseError::UnexpectedEnd,
+                rule,
+                ..
+            }) if rule == "A =>"
+        ));
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests_serde {
+    use super::*;
+    use input::{Input, Scenario};
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    fn sorted(map: &RuleMap) -> Vec<TruthTable> {
+        let mut tables: Vec<TruthTable> = map
+            .truth_tables()
+            .into_iter()
+            .map(|t| t.as_ref().clone())
+            .collect();
+        tables.sort_unstable_by(|a, b| a.rule.cmp(&b.rule));
+        tables
+    }
+
+    #[test]
+    fn truth_table() -> Result<()> {
+        let table = TruthTable::parse("A + !(B | C) <=> D", Grammar::default())?;
+        let json = serde_json::to_string(&table)?;
+        assert_eq!(serde_json::from_str::<TruthTable>(&json)?, table);
+        Ok(())
+    }
+
+    #[test]
+    fn rule_map() -> Result<()> {
+        let map = RuleMap::try_from(vec!["A + B => C", "C | D => E", "E => A"])?;
+        let json = serde_json::to_string(&map)?;
+        let result: RuleMap = serde_json::from_str(&json)?;
+        assert_eq!(sorted(&result), sorted(&map));
+        assert_eq!(result.grammar, map.grammar);
+        assert_eq!(serde_json::to_string(&result)?, json);
+        Ok(())
+    }
+
+    #[test]
+    fn rule_map_sharing() -> Result<()> {
+        let map = RuleMap::try_from(vec!["A + B => C"])?;
+        let json = serde_json::to_string(&map)?;
+        // Each table is written once, not once for every variable
+        assert_eq!(json.matches("\"rule\"").count(), 1);
+
+        let result: RuleMap = serde_json::from_str(&json)?;
+        let tables: Vec<&Arc<TruthTable>> = ["A", "B", "C"]
+            .iter()
+            .flat_map(|ident| result.tables(ident))
+            .collect();
+        assert_eq!(tables.len(), 3);
+        assert!(tables.iter().all(|t| Arc::ptr_eq(t, tables[0])));
+        // Once under each variable and once under the id of the rule
+        assert_eq!(Arc::strong_count(tables[0]), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn rule_map_ids() -> Result<()> {
+        let mut map = RuleMap::try_from(vec!["A => B", "B => C", "C => D"])?;
+        let ids: Vec<RuleId> = map.rules().map(|(id, _)| id).collect();
+        map.remove(ids[1]);
+        let mut result: RuleMap = serde_json::from_str(&serde_json::to_string(&map)?)?;
+        let rules: Vec<(RuleId, &str)> = result
+            .rules()
+            .map(|(id, table)| (id, table.rule.as_str()))
+            .collect();
+        assert_eq!(rules, vec![(ids[0], "A => B"), (ids[2], "C => D")]);
+        assert_eq!(result.insert("D => E")?, map.insert("D => E")?);
+        Ok(())
+    }
+
+    #[test]
+    fn grammar() -> Result<()> {
+        let mut map = RuleMap::with_grammar(Grammar::LeftToRight);
+        map.insert("A | B + C => D")?;
+        let result: RuleMap = serde_json::from_str(&serde_json::to_string(&map)?)?;
+        assert_eq!(result.grammar, Grammar::LeftToRight);
+        assert_eq!(sorted(&result), sorted(&map));
+        Ok(())
+    }
+
+    #[test]
+    fn rule_map_limits() -> Result<()> {
+        let limits = Limits {
+            rule_variables: 2,
+            ..Limits::default()
+        };
+        let mut map = RuleMap::with_limits(Grammar::default(), limits);
+        map.insert("A => B")?;
+        let json = serde_json::to_string(&map)?;
+        let result: RuleMap = serde_json::from_str(&json)?;
+        assert_eq!(result.limits(), &limits);
+
+        // A rule that exceeds the limits of the map is refused
+        let json = json.replace("\"rule_variables\":2", "\"rule_variables\":1");
+        let result = serde_json::from_str::<RuleMap>(&json);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Rule has 2 identifiers, at most 1 are supported"));
+        Ok(())
+    }
+
+    #[test]
+    fn error_rule_map_mismatch() -> Result<()> {
+        let map = RuleMap::try_from(vec!["A => B"])?;
+        let json =
+            serde_json::to_string(&map)?.replace("\"rule\":\"A => B\"", "\"rule\":\"A => C\"");
+        let result = serde_json::from_str::<RuleMap>(&json);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Truth table does not match its rule: 'A => C'"));
+        Ok(())
+    }
+
+    #[test]
+    fn error_truth_table_results() -> Result<()> {
+        let table = TruthTable::try_from("A => B")?;
+        let json = serde_json::to_string(&table)?.replace("[true,true,false,true]", "[true]");
+        let result = serde_json::from_str::<TruthTable>(&json);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Truth table does not match its rule: 'A => B'"));
+        Ok(())
+    }
+
+    #[test]
+    fn error_truth_table_variable() -> Result<()> {
+        let table = TruthTable::try_from("A => B")?;
+        let json = serde_json::to_string(&table)?.replace("{\"Var\":1}", "{\"Var\":5}");
+        let result = serde_json::from_str::<TruthTable>(&json);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Truth table has no variable 5: 'A => B'"));
+        Ok(())
+    }
+
+    #[test]
+    fn error_truth_table_too_many_variables() -> Result<()> {
+        let table = TruthTable::try_from("A => B")?;
+        let variables: Vec<String> = (0..64).map(|i| format!("X{}", i)).collect();
+        let json = serde_json::to_string(&table)?.replace(
+            "\"variables\":[\"A\",\"B\"]",
+            &format!("\"variables\":{}", serde_json::to_string(&variables)?),
+        );
+        let result = serde_json::from_str::<TruthTable>(&json);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Rule has 64 identifiers, at most 24 are supported"));
+        Ok(())
+    }
+
+    #[test]
+    fn input() -> Result<()> {
+        let input = Input {
+            rules: vec!["A=>B".to_string()],
+            scenarios: vec![Scenario::new(vec!["A".to_string()], vec!["B".to_string()])],
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&input)?;
         assert_eq!(
-            result.unwrap_err().to_string(),
-            "Failed to create truth table from: 'A =>'"
+            json,
+            r#"{"rules":["A=>B"],"scenarios":[{"facts":["A"],"queries":["B"]}]}"#
         );
+        assert!(serde_json::from_str::<Input>(&json)? == input);
+        Ok(())
     }
 }
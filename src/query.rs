@@ -0,0 +1,158 @@
+use crate::*;
+use error::ExpertSystemError;
+use input::Scenario;
+use parser::{RuleMap, Truth};
+use resolver::Resolver;
+
+use std::collections::BTreeSet;
+
+// Identifiers that are known to be true, the facts that queries on a RuleMap are answered
+// with. Every other identifier is false unless a rule derives it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FactSet {
+    facts: BTreeSet<String>,
+}
+
+impl FactSet {
+    pub fn new() -> Self {
+        FactSet::default()
+    }
+
+    // Adds `ident` as a fact, returns false if it already was one
+    pub fn insert(&mut self, ident: impl Into<String>) -> bool {
+        self.facts.insert(ident.into())
+    }
+
+    // Retracts the fact `ident`, returns false if it was not a fact
+    pub fn remove(&mut self, ident: &str) -> bool {
+        self.facts.remove(ident)
+    }
+
+    pub fn contains(&self, ident: &str) -> bool {
+        self.facts.contains(ident)
+    }
+
+    pub fn len(&self) -> usize {
+        self.facts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.facts.is_empty()
+    }
+
+    // Returns the facts in alphabetical order
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.facts.iter()
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for FactSet {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        FactSet {
+            facts: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl<S: Into<String>> Extend<S> for FactSet {
+    fn extend<I: IntoIterator<Item = S>>(&mut self, iter: I) {
+        self.facts.extend(iter.into_iter().map(Into::into));
+    }
+}
+
+impl<'a> IntoIterator for &'a FactSet {
+    type Item = &'a String;
+    type IntoIter = std::collections::btree_set::Iter<'a, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.facts.iter()
+    }
+}
+
+// The facts of a scenario of an input
+impl From<&Scenario> for FactSet {
+    fn from(scenario: &Scenario) -> Self {
+        scenario.facts.iter().cloned().collect()
+    }
+}
+
+// Queries answered with the backward Resolver. A contradiction is only reported when it
+// involves the rules used to answer the queries, Resolver::check verifies all rules.
+impl RuleMap {
+    pub fn query(&self, facts: &FactSet, ident: &str) -> Result<Truth, ExpertSystemError> {
+        Resolver::new(self, facts).resolve(ident)
+    }
+
+    // Answers every query in `idents` with the same facts, in the order they are given
+    pub fn query_all<I>(&self, facts: &FactSet, idents: I) -> Result<Vec<Truth>, ExpertSystemError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut resolver = Resolver::new(self, facts);
+        idents
+            .into_iter()
+            .map(|ident| resolver.resolve(ident.as_ref()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests_query {
+    use super::*;
+    use input::Input;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn fact_set() {
+        let mut facts: FactSet = ["B", "A"].into_iter().collect();
+        assert!(facts.insert("C"));
+        assert!(!facts.insert("A"));
+        assert!(facts.remove("B"));
+        assert!(!facts.remove("B"));
+        assert!(facts.contains("A") && !facts.contains("B"));
+        assert_eq!(facts.len(), 2);
+        assert_eq!(facts.iter().collect::<Vec<_>>(), vec!["A", "C"]);
+        assert!(FactSet::new().is_empty());
+    }
+
+    #[test]
+    fn query() -> Result<()> {
+        let map = RuleMap::try_from(vec!["A + B => C", "C | D => E", "E => !F"])?;
+        let facts: FactSet = ["A", "B"].into_iter().collect();
+        assert_eq!(map.query(&facts, "E")?, Truth::True);
+        assert_eq!(map.query(&facts, "F")?, Truth::False);
+        assert_eq!(map.query(&FactSet::new(), "E")?, Truth::False);
+        assert_eq!(
+            map.query_all(&facts, ["C", "D", "E", "F"])?,
+            vec![Truth::True, Truth::False, Truth::True, Truth::False]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn query_scenarios() -> Result<()> {
+        let input = Input::from_lines(&["A | B => C", "=A", "?C", "=D", "?C"], "<input>")?;
+        let map = RuleMap::try_from(input.rules.clone())?;
+        let results: Vec<Vec<Truth>> = input
+            .scenarios
+            .iter()
+            .map(|scenario| map.query_all(&FactSet::from(scenario), &scenario.queries))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(results, vec![vec![Truth::True], vec![Truth::False]]);
+        Ok(())
+    }
+
+    #[test]
+    fn error_contradiction() -> Result<()> {
+        let map = RuleMap::try_from(vec!["A => C", "B => !C"])?;
+        let facts: FactSet = ["A", "B"].into_iter().collect();
+        assert!(matches!(
+            map.query(&facts, "C"),
+            Err(ExpertSystemError::Contradiction(_))
+        ));
+        Ok(())
+    }
+}
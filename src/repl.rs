@@ -0,0 +1,427 @@
+use crate::*;
+use error::{ExpertSystemError, InputError};
+use input::{format_identifiers, parse_identifiers, Input};
+use limits::Limits;
+use parser::{Grammar, RuleId, RuleMap};
+use resolver::Resolver;
+
+use anyhow::{anyhow, Context, Result};
+use std::{
+    fs,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+pub const REPL_HELP: &str = indoc! {"
+    <RULE>           Add a rule, e.g. A + B => C
+    =<FACTS>         Set the facts, e.g. =ABG
+    ?<QUERIES>       Answer queries, e.g. ?GVX
+    :facts           Print the facts
+    :rules           Print the rules with their ids
+    :remove <ID>     Remove the rule with ID
+    :assert <FACTS>  Add facts, e.g. :assert HasFever Cough
+    :retract <FACTS> Remove facts
+    :reset           Remove all rules and facts
+    :load <FILE>     Replace the rules and facts with those of FILE
+    :save <FILE>     Write the rules and facts to FILE
+    :help            Print this help
+    :quit            Exit
+"};
+
+const PROMPT: &str = "> ";
+
+// Repl keeps a knowledge base that can be changed and queried one line at a time.
+// Rules, facts and queries use the same syntax as an input file, lines starting with
+// a colon are commands.
+#[derive(Default)]
+pub struct Repl {
+    map: RuleMap,
+    facts: Vec<String>,
+    grammar: Grammar,
+    limits: Limits,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Repl::default()
+    }
+
+    // Creates a session in which rules are parsed using `grammar`
+    pub fn with_grammar(grammar: Grammar) -> Self {
+        Repl::with_limits(grammar, Limits::default())
+    }
+
+    // Creates a session that refuses rules exceeding `limits`, which also bound its queries
+    pub fn with_limits(grammar: Grammar, limits: Limits) -> Self {
+        Repl {
+            map: RuleMap::with_limits(grammar, limits),
+            grammar,
+            limits,
+            ..Repl::default()
+        }
+    }
+
+    // Reads lines from `input` until it ends or `:quit` is entered, errors in a line are
+    // written to `output` and do not stop the loop.
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> Result<()> {
+        write!(output, "{}", PROMPT)?;
+        output.flush()?;
+        for line in input.lines() {
+            let line = line.context("Failed to read line")?;
+            if matches!(line.trim(), ":quit" | ":q") {
+                return Ok(());
+            }
+            match self.execute(&line) {
+                Ok(result) => write!(output, "{}", result)?,
+                Err(err) => writeln!(output, "Error: {:#}", err)?,
+            }
+            write!(output, "{}", PROMPT)?;
+            output.flush()?;
+        }
+        writeln!(output)?;
+        Ok(())
+    }
+
+    // Executes a single line and returns the text to print
+    pub fn execute(&mut self, line: &str) -> Result<String> {
+        let line = line.trim();
+        if let Some(command) = line.strip_prefix(':') {
+            // The argument is the rest of the line, so that it can hold several facts or a
+            // path containing spaces
+            let (name, arg) = match command.split_once(char::is_whitespace) {
+                Some((name, arg)) => (name, Some(arg.trim())),
+                None => (command, None),
+            };
+            return self.command(name, arg.filter(|arg| !arg.is_empty()));
+        }
+
+        let mut line = match sanitize::sanitize_lines(&[line]).pop() {
+            Some(line) => line,
+            None => return Ok(String::new()),
+        };
+        match line.chars().next() {
+            Some('=') => {
+                line.remove(0);
+                self.facts = parse_facts(&line)?;
+                Ok(String::new())
+            }
+            Some('?') => {
+                line.remove(0);
+                let queries = parse_identifiers(&line, InputError::InvalidQuery)
+                    .map_err(|(err, _)| ExpertSystemError::from(err))?;
+                self.query(&queries)
+            }
+            _ => {
+                self.map
+                    .insert(line.as_str())
+                    .context(format!("Failed to create truth table from: '{}'", line))?;
+                Ok(String::new())
+            }
+        }
+    }
+
+    fn command(&mut self, name: &str, arg: Option<&str>) -> Result<String> {
+        match (name, arg) {
+            ("facts", None) => Ok(format!("={}\n", format_identifiers(&self.facts))),
+            ("rules", None) => Ok(self
+                .map
+                .rules()
+                .map(|(id, table)| format!("{}: {}\n", id, table.rule))
+                .collect()),
+            ("remove", Some(id)) => {
+                let rule_id: RuleId = id
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid rule id: '{}'", id))?;
+                match self.map.remove(rule_id) {
+                    Some(_) => Ok(String::new()),
+                    None => Err(anyhow!("No rule with id {}", rule_id)),
+                }
+            }
+            ("assert", Some(facts)) => {
+                for fact in parse_facts(facts)? {
+                    if !self.facts.contains(&fact) {
+                        self.facts.push(fact);
+                    }
+                }
+                Ok(String::new())
+            }
+            ("retract", Some(facts)) => {
+                let facts = parse_facts(facts)?;
+                self.facts.retain(|c| !facts.contains(c));
+                Ok(String::new())
+            }
+            ("reset", None) => {
+                *self = Repl::with_limits(self.grammar, self.limits);
+                Ok(String::new())
+            }
+            ("load", Some(file)) => {
+                self.load(file)?;
+                Ok(String::new())
+            }
+            ("save", Some(file)) => {
+                self.save(file)?;
+                Ok(String::new())
+            }
+            ("help", None) => Ok(REPL_HELP.to_string()),
+            ("assert" | "retract" | "remove" | "load" | "save", None) => {
+                Err(anyhow!("Missing argument for :{}", name))
+            }
+            ("facts" | "rules" | "reset" | "help", Some(arg)) => {
+                Err(anyhow!("Unexpected argument: '{}'", arg))
+            }
+            _ => Err(anyhow!("Unknown command: ':{}'", name)),
+        }
+    }
+
+    fn query(&self, queries: &[String]) -> Result<String> {
+        let mut resolver = Resolver::new(&self.map, &self.facts);
+        let mut result = String::new();
+        for query in queries.iter() {
+            result.push_str(&format!("{} is {}\n", query, resolver.resolve(query)?));
+        }
+        Ok(result)
+    }
+
+    // Replaces the rules and facts with the ones of an input file, the facts are those of its
+    // first scenario and its queries are ignored
+    pub fn load(&mut self, file: impl AsRef<Path>) -> Result<()> {
+        let input = Input::try_from(PathBuf::from(file.as_ref()))
+            .context(format!("Unable to load: '{}'", file.as_ref().display()))?;
+        let mut map = RuleMap::with_limits(self.grammar, self.limits);
+        map.insert_vec(input.rules)
+            .context("Failed to parse rule")?;
+        self.map = map;
+        self.facts = input
+            .scenarios
+            .into_iter()
+            .next()
+            .map_or(vec![], |s| s.facts);
+        Ok(())
+    }
+
+    // Writes the rules and facts as an input file without queries
+    pub fn save(&self, file: impl AsRef<Path>) -> Result<()> {
+        let mut content = String::new();
+        for (_, table) in self.map.rules() {
+            content.push_str(&format!("{}\n", table.rule));
+        }
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        content.push_str(&format!("={}\n?\n", format_identifiers(&self.facts)));
+        fs::write(file.as_ref(), content)
+            .context(format!("Unable to save: '{}'", file.as_ref().display()))
+    }
+}
+
+// Parses facts the way the `=` line of an input file is parsed, `facts` can still hold
+// whitespace and a comment
+fn parse_facts(facts: &str) -> Result<Vec<String>> {
+    let (facts, _) = sanitize::sanitize_line(facts);
+    Ok(parse_identifiers(&facts, InputError::InvalidFact)
+        .map_err(|(err, _)| ExpertSystemError::from(err))?)
+}
+
+#[cfg(test)]
+mod tests_repl {
+    use super::*;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use std::io::Cursor;
+
+    fn run(lines: &[&str]) -> Result<String> {
+        let mut output: Vec<u8> = vec![];
+        Repl::new().run(Cursor::new(lines.join("\n")), &mut output)?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    #[test]
+    fn rules_facts_queries() -> Result<()> {
+        let mut repl = Repl::new();
+        assert_eq!(repl.execute("A + B => C  # comment")?, "");
+        assert_eq!(repl.execute("?C")?, "C is false\n");
+        assert_eq!(repl.execute("=AB")?, "");
+        assert_eq!(repl.execute("?CA")?, "C is true\nA is true\n");
+        assert_eq!(repl.execute(":rules")?, "0: A+B=>C\n");
+        assert_eq!(repl.execute(":facts")?, "=AB\n");
+        Ok(())
+    }
+
+    #[test]
+    fn empty_line() -> Result<()> {
+        assert_eq!(Repl::new().execute("  # nothing")?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn retract() -> Result<()> {
+        let mut repl = Repl::new();
+        repl.execute("A => B")?;
+        repl.execute("=AC")?;
+        assert_eq!(repl.execute("?B")?, "B is true\n");
+        repl.execute(":retract A")?;
+        assert_eq!(repl.execute(":facts")?, "=C\n");
+        assert_eq!(repl.execute("?B")?, "B is false\n");
+        Ok(())
+    }
+
+    #[test]
+    fn assert_and_retract_several() -> Result<()> {
+        let mut repl = Repl::new();
+        repl.execute("HasFever + Cough => Flu")?;
+        repl.execute(":assert HasFever  Cough Sneeze # comment")?;
+        repl.execute(":assert Cough")?;
+        assert_eq!(repl.execute(":facts")?, "=HasFever Cough Sneeze\n");
+        assert_eq!(repl.execute("?Flu")?, "Flu is true\n");
+        repl.execute(":retract HasFever Cough")?;
+        assert_eq!(repl.execute(":facts")?, "=Sneeze\n");
+        assert_eq!(repl.execute("?Flu")?, "Flu is false\n");
+        Ok(())
+    }
+
+    #[test]
+    fn remove_rule() -> Result<()> {
+        let mut repl = Repl::new();
+        repl.execute("A => B")?;
+        repl.execute("B => C")?;
+        repl.execute("=A")?;
+        assert_eq!(repl.execute("?C")?, "C is true\n");
+        repl.execute(":remove 0")?;
+        // Ids are kept after a removal and not reused
+        repl.execute("C => D")?;
+        assert_eq!(repl.execute(":rules")?, "1: B=>C\n2: C=>D\n");
+        assert_eq!(repl.execute("?BC")?, "B is false\nC is false\n");
+
+        let result = repl.execute(":remove 0");
+        assert_eq!(result.unwrap_err().to_string(), "No rule with id 0");
+        let result = repl.execute(":remove first");
+        assert_eq!(result.unwrap_err().to_string(), "Invalid rule id: 'first'");
+        Ok(())
+    }
+
+    #[test]
+    fn reset() -> Result<()> {
+        let mut repl = Repl::new();
+        repl.execute("A => B")?;
+        repl.execute("=A")?;
+        repl.execute(":reset")?;
+        assert_eq!(repl.execute(":rules")?, "");
+        assert_eq!(repl.execute(":facts")?, "=\n");
+        assert_eq!(repl.execute("?B")?, "B is false\n");
+        Ok(())
+    }
+
+    #[test]
+    fn save_and_load() -> Result<()> {
+        let file = std::env::temp_dir().join("expert_system_repl_save_and_load.txt");
+        let mut repl = Repl::new();
+        repl.execute("A => B")?;
+        repl.execute("B | C => D")?;
+        repl.execute("=A")?;
+        repl.execute(&format!(":save {}", file.display()))?;
+        assert_eq!(fs::read_to_string(&file)?, "A=>B\nB|C=>D\n\n=A\n?\n");
+
+        let mut loaded = Repl::new();
+        loaded.execute("E => F")?;
+        loaded.execute(&format!(":load {}", file.display()))?;
+        assert_eq!(loaded.execute(":rules")?, "0: A=>B\n1: B|C=>D\n");
+        assert_eq!(loaded.execute("?DF")?, "D is true\nF is false\n");
+        fs::remove_file(file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn save_and_load_path_with_spaces() -> Result<()> {
+        let file = std::env::temp_dir().join("expert_system repl path with spaces.txt");
+        let mut repl = Repl::new();
+        repl.execute("A => B")?;
+        repl.execute("=A")?;
+        repl.execute(&format!(":save {}", file.display()))?;
+
+        let mut loaded = Repl::new();
+        loaded.execute(&format!(":load {}", file.display()))?;
+        assert_eq!(loaded.execute("?B")?, "B is true\n");
+        fs::remove_file(file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn run_loop() -> Result<()> {
+        let result = run(&["A => B", "=A", "?B", ":quit", "?A"])?;
+        assert_eq!(result, "> > > B is true\n> ");
+        Ok(())
+    }
+
+    #[test]
+    fn run_errors_continue() -> Result<()> {
+        let result = run(&["A => ", "?b-c", ":foo", "?A"])?;
+        assert_eq!(
+            result,
+            "> Error: Failed to create truth table from: 'A=>': \
+             Unexpected end of token list\n\
+             > Error: Invalid identifier in query: 'b-c'\n\
+             > Error: Unknown command: ':foo'\n\
+             > A is false\n\
+             > \n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn error_missing_argument() {
+        let result = Repl::new().execute(":load");
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Missing argument for :load"
+        );
+    }
+
+    #[test]
+    fn error_unexpected_argument() {
+        let result = Repl::new().execute(":rules all");
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Unexpected argument: 'all'"
+        );
+    }
+
+    #[test]
+    fn error_load_non_exist() {
+        let file = crate::test_utils::input_file_path("repl/non_exist.txt");
+        let result = Repl::new().execute(&format!(":load {}", file.display()));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn limits() -> Result<()> {
+        let limits = Limits {
+            rule_variables: 2,
+            ..Limits::default()
+        };
+        let mut repl = Repl::with_limits(Grammar::default(), limits);
+        repl.execute("A => B")?;
+        assert!(repl.execute("A + B => C").is_err());
+        repl.execute(":reset")?;
+        assert!(repl.execute("A + B => C").is_err());
+
+        let file = std::env::temp_dir().join("expert_system_repl_limits.txt");
+        fs::write(&file, "A + B => C\n\n=A\n?C\n")?;
+        let result = repl.execute(&format!(":load {}", file.display()));
+        fs::remove_file(file)?;
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn error_contradiction() -> Result<()> {
+        let mut repl = Repl::new();
+        repl.execute("A => !B")?;
+        repl.execute("=AB")?;
+        let result = repl.execute("?B");
+        assert!(result.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use crate::*;
+use explain::Explanation;
+use input::Input;
+use lint::Warning;
+use parser::Truth;
+
+// Results of solving an input, in a form that can be written out as a whole, see
+// json::ToJson. `warnings` holds what lint::lint found in the input. With the serde feature
+// it serializes to the same document as ToJson.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Report {
+    pub rules: Vec<String>,
+    pub scenarios: Vec<ScenarioReport>,
+    pub warnings: Vec<Warning>,
+}
+
+// Results of a single scenario of the input. `derived` is only set by engines that derive
+// every identifier up front.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ScenarioReport {
+    pub facts: Vec<String>,
+    pub queries: Vec<QueryResult>,
+    pub derived: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct QueryResult {
+    pub query: String,
+    pub value: Truth,
+    pub explanation: Option<Explanation>,
+}
+
+impl Report {
+    // Creates a report with a scenario without results for every scenario of `input`
+    pub fn new(input: &Input) -> Self {
+        Report {
+            rules: input.rules.clone(),
+            scenarios: input
+                .scenarios
+                .iter()
+                .map(|scenario| ScenarioReport {
+                    facts: scenario.facts.clone(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl ScenarioReport {
+    pub fn push(&mut self, query: &str, value: Truth, explanation: Option<Explanation>) {
+        self.queries.push(QueryResult {
+            query: query.to_string(),
+            value,
+            explanation,
+        });
+    }
+}
@@ -0,0 +1,767 @@
+use crate::*;
+use error::{ExpertSystemError, Limit};
+use explain::{Explanation, Reason};
+use limits::Limits;
+use parser::{RuleMap, Truth, TruthTable};
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+// Resolver answers queries on a RuleMap using backward chaining. To find the value of an
+// identifier it walks all truth tables that can derive it, recursively resolves the other
+// identifiers of each table and filters the rows of the table that are consistent with them.
+// An identifier is true or false when a table only leaves rows with that value, and
+// undetermined when the rows it is forced to be true in depend on identifiers that are
+// themselves undetermined (`A => B | C`). Identifiers that are not a fact and can not be
+// derived from any rule are false.
+//
+// While an identifier is being resolved it is kept on a stack, running into it again
+// means the rules form a cycle. In that case the identifier is assumed false when it is
+// used as a source of the table and left open otherwise. Results that depend on such an
+// assumption are not cached, since the assumption might not hold once resolved.
+//
+// The stack is bounded by the depth of the limits of the map, and resolving fails once the
+// timeout of those limits has passed since the resolver was created.
+pub struct Resolver<'a> {
+    map: &'a RuleMap,
+    facts: HashSet<String>,
+    resolved: HashMap<String, Truth>,
+    reasons: HashMap<String, &'a TruthTable>,
+    stack: Vec<String>,
+    low_link: usize,
+    start: Instant,
+}
+
+// Error returned when rules and facts can not all hold at the same time, it names the
+// rules involved and the assignment of identifiers they can not be satisfied with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contradiction {
+    pub rules: Vec<String>,
+    pub assignment: Vec<(String, bool)>,
+}
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rules: Vec<String> = self.rules.iter().map(|r| format!("'{}'", r)).collect();
+        write!(f, "Contradiction in {}", rules.join(" and "))?;
+        let mut assignment = self.assignment.iter().peekable();
+        if assignment.peek().is_some() {
+            write!(f, " when")?;
+        }
+        while let Some((ident, value)) = assignment.next() {
+            write!(f, " {} is {}", ident, value)?;
+            if assignment.peek().is_some() {
+                write!(f, ",")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Contradiction {}
+
+// Value that a single truth table forces an identifier to, along with the values of the
+// other identifiers of the table it was derived with. `assumed` is set when part of
+// that assignment is based on a cycle assumption, either directly or through a value that
+// could not be cached.
+struct Derivation<'a> {
+    table: &'a TruthTable,
+    value: Truth,
+    assignment: Vec<(String, bool)>,
+    assumed: bool,
+}
+
+impl Contradiction {
+    fn new<'a>(derivations: impl IntoIterator<Item = &'a Derivation<'a>>) -> Self {
+        let mut rules = Vec::new();
+        let mut assignment = Vec::new();
+        for derivation in derivations {
+            rules.push(derivation.table.rule.clone());
+            assignment.extend(derivation.assignment.iter().cloned());
+        }
+        assignment.sort_unstable();
+        assignment.dedup();
+        Contradiction { rules, assignment }
+    }
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new<I>(map: &'a RuleMap, facts: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Resolver {
+            map,
+            facts: facts.into_iter().map(Into::into).collect(),
+            resolved: HashMap::new(),
+            reasons: HashMap::new(),
+            stack: Vec::new(),
+            low_link: usize::MAX,
+            start: Instant::now(),
+        }
+    }
+
+    // Resolves every identifier and verifies that each rule can still be satisfied by the
+    // resulting values.
+    pub fn check(&mut self) -> Result<(), ExpertSystemError> {
+        let map = self.map;
+        let mut identifiers: Vec<String> = map.identifiers().cloned().collect();
+        identifiers.extend(self.facts.iter().cloned());
+        identifiers.sort_unstable();
+        for ident in identifiers {
+            self.resolve(&ident)?;
+        }
+
+        let mut tables: Vec<&Arc<TruthTable>> = map.truth_tables().into_iter().collect();
+        tables.sort_unstable_by(|a, b| a.rule.cmp(&b.rule));
+        for table in tables {
+            let mut assignment = Vec::new();
+            for v in table.variables.iter() {
+                match self.resolve(v)? {
+                    Truth::Undetermined => {}
+                    t => assignment.push((v.clone(), t == Truth::True)),
+                }
+            }
+            let satisfiable = (0..table.results.len())
+                .filter(|row| table.results[*row])
+                .any(|row| {
+                    assignment.iter().all(|(v, value)| {
+                        let index = table.variables.iter().position(|i| i == v);
+                        index.is_some_and(|i| table.value(row, i) == *value)
+                    })
+                });
+            if !satisfiable {
+                return Err(Contradiction {
+                    rules: vec![table.rule.clone()],
+                    assignment,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the truth tables that can be used to derive `ident`, ordered by their rule
+    pub fn tables(&self, ident: &str) -> Vec<&'a TruthTable> {
+        let mut tables: Vec<&TruthTable> = self
+            .map
+            .tables(ident)
+            .filter(|table| table.sources(ident).is_some())
+            .map(|table| table.as_ref())
+            .collect();
+        tables.sort_unstable_by(|a, b| a.rule.cmp(&b.rule));
+        tables
+    }
+
+    pub fn resolve(&mut self, ident: &str) -> Result<Truth, ExpertSystemError> {
+        if let Some(value) = self.resolved.get(ident) {
+            return Ok(*value);
+        }
+        // An error leaves the identifiers that were being resolved without restoring their
+        // low link, so it is restored here for the resolver to be used again
+        let low_link = self.low_link;
+        let result = self.resolve_uncached(ident);
+        if result.is_err() {
+            self.low_link = low_link;
+        }
+        result
+    }
+
+    fn resolve_uncached(&mut self, ident: &str) -> Result<Truth, ExpertSystemError> {
+        let depth = self.stack.len();
+        let limits = self.map.limits();
+        Limits::check(Limit::Depth, limits.depth, depth + 1)?;
+        limits.check_timeout(self.start)?;
+        let low_link = std::mem::replace(&mut self.low_link, usize::MAX);
+        self.stack.push(ident.to_string());
+        let map = self.map;
+        let derivations: Result<Vec<Derivation<'a>>, ExpertSystemError> = map
+            .tables(ident)
+            .filter_map(|table| self.derive(table, ident).transpose())
+            .collect();
+        self.stack.pop();
+        let derivations = derivations?;
+
+        let forced = |value: Truth| {
+            derivations
+                .iter()
+                .filter(|d| !d.assumed && d.value == value)
+                .min_by(|a, b| a.table.rule.cmp(&b.table.rule))
+        };
+        let value = if self.facts.contains(ident) {
+            if let Some(derivation) = forced(Truth::False) {
+                let mut contradiction = Contradiction::new([derivation]);
+                contradiction.assignment.push((ident.to_string(), true));
+                contradiction.assignment.sort_unstable();
+                return Err(contradiction.into());
+            }
+            Truth::True
+        } else {
+            if let (Some(t), Some(f)) = (forced(Truth::True), forced(Truth::False)) {
+                return Err(Contradiction::new([t, f]).into());
+            }
+            [Truth::True, Truth::False, Truth::Undetermined]
+                .into_iter()
+                .find(|t| derivations.iter().any(|d| d.value == *t))
+                .unwrap_or(Truth::False)
+        };
+
+        if self.low_link >= depth {
+            self.resolved.insert(ident.to_string(), value);
+            let reason = derivations
+                .iter()
+                .filter(|d| d.value == value)
+                .min_by(|a, b| (a.assumed, &a.table.rule).cmp(&(b.assumed, &b.table.rule)));
+            if let (false, Some(reason)) = (self.facts.contains(ident), reason) {
+                self.reasons.insert(ident.to_string(), reason.table);
+            }
+            self.low_link = low_link;
+        } else {
+            self.low_link = self.low_link.min(low_link);
+        }
+        Ok(value)
+    }
+
+    // Resolves `ident` and explains its value by the rule that decided it, recursively
+    // explaining the other identifiers of that rule.
+    pub fn explain(&mut self, ident: &str) -> Result<Explanation, ExpertSystemError> {
+        self.explain_path(ident, &mut Vec::new())
+    }
+
+    fn explain_path(
+        &mut self,
+        ident: &str,
+        path: &mut Vec<String>,
+    ) -> Result<Explanation, ExpertSystemError> {
+        let value = self.resolve(ident)?;
+        let reason = if path.iter().any(|p| p == ident) {
+            Reason::Cycle
+        } else if self.facts.contains(ident) {
+            Reason::Fact
+        } else if let Some(table) = self.reasons.get(ident).copied() {
+            path.push(ident.to_string());
+            let premises: Result<Vec<Explanation>, ExpertSystemError> = table
+                .variables
+                .iter()
+                .filter(|v| *v != ident)
+                .map(|v| self.explain_path(v, path))
+                .collect();
+            path.pop();
+            Reason::Rule {
+                rule: table.rule.clone(),
+                premises: premises?,
+            }
+        } else {
+            Reason::Default
+        };
+        Ok(Explanation {
+            ident: ident.to_string(),
+            value,
+            reason,
+        })
+    }
+
+    // Returns the value `ident` is forced to by `table`, None if the table does not
+    // constrain it.
+    fn derive(
+        &mut self,
+        table: &'a TruthTable,
+        ident: &str,
+    ) -> Result<Option<Derivation<'a>>, ExpertSystemError> {
+        let sources = match table.sources(ident) {
+            Some(sources) => sources,
+            None => return Ok(None),
+        };
+        let mut known: Vec<Option<bool>> = Vec::with_capacity(table.variables.len());
+        let mut assignment = Vec::new();
+        let mut assumed = false;
+        for v in table.variables.iter() {
+            let value = if v == ident {
+                None
+            } else if self.facts.contains(v) {
+                Some(true)
+            } else if let Some(depth) = self.stack.iter().position(|s| s == v) {
+                self.low_link = self.low_link.min(depth);
+                assumed |= sources.contains(v);
+                sources.contains(v).then_some(false)
+            } else {
+                let value = self.resolve(v)?;
+                assumed |= !self.resolved.contains_key(v);
+                match value {
+                    Truth::Undetermined => None,
+                    t => Some(t == Truth::True),
+                }
+            };
+            if let Some(value) = value {
+                assignment.push((v.clone(), value));
+            }
+            known.push(value);
+        }
+
+        // Rows are grouped by the values of the open identifiers, if `ident` is only true
+        // in one of these groups it depends on them whether `ident` holds.
+        let index = table.variables.iter().position(|v| v == ident).unwrap();
+        let mut possible = (false, false);
+        let mut groups: HashMap<usize, (bool, bool)> = HashMap::new();
+        for (row, _) in table.results.iter().enumerate().filter(|(_, r)| **r) {
+            let consistent = known
+                .iter()
+                .enumerate()
+                .all(|(i, k)| k.is_none_or(|k| k == table.value(row, i)));
+            if consistent {
+                let group = groups.entry(row & !(1 << (table.variables.len() - 1 - index)));
+                let group = group.or_insert((false, false));
+                match table.value(row, index) {
+                    true => (possible.1, group.1) = (true, true),
+                    false => (possible.0, group.0) = (true, true),
+                }
+            }
+        }
+        let value = match possible {
+            (false, false) if !assumed => {
+                return Err(Contradiction {
+                    rules: vec![table.rule.clone()],
+                    assignment,
+                }
+                .into())
+            }
+            (false, true) => Truth::True,
+            (true, false) => Truth::False,
+            _ if groups.values().any(|g| *g == (false, true)) => Truth::Undetermined,
+            _ => return Ok(None),
+        };
+        Ok(Some(Derivation {
+            table,
+            value,
+            assignment,
+            assumed,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests_resolver {
+    use super::*;
+    use parser::Grammar;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn resolve(rules: Vec<&str>, facts: &str, queries: &str) -> Result<String> {
+        let map = RuleMap::try_from(rules)?;
+        let mut resolver = Resolver::new(&map, facts.chars());
+        queries
+            .chars()
+            .map(|q| {
+                Ok(match resolver.resolve(&q.to_string())? {
+                    Truth::True => '1',
+                    Truth::False => '0',
+                    Truth::Undetermined => '?',
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn facts() -> Result<()> {
+        assert_eq!(resolve(vec![], "AB", "ABC")?, "110");
+        Ok(())
+    }
+
+    #[test]
+    fn simple() -> Result<()> {
+        assert_eq!(resolve(vec!["A => Z"], "A", "Z")?, "1");
+        assert_eq!(resolve(vec!["A => Z"], "", "Z")?, "0");
+        Ok(())
+    }
+
+    #[test]
+    fn chain() -> Result<()> {
+        let rules = vec!["A => B", "B => C", "C => D"];
+        assert_eq!(resolve(rules.clone(), "A", "ABCD")?, "1111");
+        assert_eq!(resolve(rules, "B", "ABCD")?, "0111");
+        Ok(())
+    }
+
+    #[test]
+    fn and() -> Result<()> {
+        let rules = vec!["A + B => C"];
+        assert_eq!(resolve(rules.clone(), "", "C")?, "0");
+        assert_eq!(resolve(rules.clone(), "A", "C")?, "0");
+        assert_eq!(resolve(rules.clone(), "B", "C")?, "0");
+        assert_eq!(resolve(rules, "AB", "C")?, "1");
+        Ok(())
+    }
+
+    #[test]
+    fn or() -> Result<()> {
+        let rules = vec!["A | B => C"];
+        assert_eq!(resolve(rules.clone(), "", "C")?, "0");
+        assert_eq!(resolve(rules.clone(), "A", "C")?, "1");
+        assert_eq!(resolve(rules.clone(), "B", "C")?, "1");
+        assert_eq!(resolve(rules, "AB", "C")?, "1");
+        Ok(())
+    }
+
+    #[test]
+    fn xor() -> Result<()> {
+        let rules = vec!["A ^ B => C"];
+        assert_eq!(resolve(rules.clone(), "", "C")?, "0");
+        assert_eq!(resolve(rules.clone(), "A", "C")?, "1");
+        assert_eq!(resolve(rules.clone(), "B", "C")?, "1");
+        assert_eq!(resolve(rules, "AB", "C")?, "0");
+        Ok(())
+    }
+
+    #[test]
+    fn not() -> Result<()> {
+        let rules = vec!["!A => B"];
+        assert_eq!(resolve(rules.clone(), "", "AB")?, "01");
+        assert_eq!(resolve(rules, "A", "AB")?, "10");
+        Ok(())
+    }
+
+    #[test]
+    fn and_in_conclusion() -> Result<()> {
+        assert_eq!(resolve(vec!["A => B + C"], "A", "BC")?, "11");
+        Ok(())
+    }
+
+    #[test]
+    fn no_contrapositive() -> Result<()> {
+        assert_eq!(resolve(vec!["A + !B => F"], "A", "BF")?, "01");
+        assert_eq!(resolve(vec!["C | !G => H"], "", "GH")?, "01");
+        Ok(())
+    }
+
+    #[test]
+    fn bi_directional() -> Result<()> {
+        let rules = vec!["A + B <=> C"];
+        assert_eq!(resolve(rules.clone(), "AB", "C")?, "1");
+        assert_eq!(resolve(rules.clone(), "C", "AB")?, "11");
+        assert_eq!(resolve(rules, "", "ABC")?, "000");
+        Ok(())
+    }
+
+    #[test]
+    fn cycle() -> Result<()> {
+        let rules = vec!["A => B", "B => A"];
+        assert_eq!(resolve(rules.clone(), "", "AB")?, "00");
+        assert_eq!(resolve(rules, "A", "BA")?, "11");
+        Ok(())
+    }
+
+    #[test]
+    fn cycle_not_cached() -> Result<()> {
+        let rules = vec!["A => B", "B => A", "C => A"];
+        assert_eq!(resolve(rules, "C", "AB")?, "11");
+        Ok(())
+    }
+
+    #[test]
+    fn subject_example() -> Result<()> {
+        let rules = vec![
+            "B => A",
+            "D + E => B",
+            "G + H => F",
+            "I + J => G",
+            "G => H",
+            "L + M => K",
+            "O + P => L + N",
+            "N => M",
+        ];
+        assert_eq!(resolve(rules.clone(), "DEIJOP", "AFKP")?, "1111");
+        assert_eq!(resolve(rules, "DEIJP", "AFKP")?, "1101");
+        Ok(())
+    }
+
+    #[test]
+    fn undetermined_or() -> Result<()> {
+        let rules = vec!["A + B => Y | Z"];
+        assert_eq!(resolve(rules.clone(), "AB", "YZ")?, "??");
+        assert_eq!(resolve(rules, "A", "YZ")?, "00");
+        Ok(())
+    }
+
+    #[test]
+    fn undetermined_xor() -> Result<()> {
+        assert_eq!(resolve(vec!["A => Y ^ Z"], "A", "YZ")?, "??");
+        Ok(())
+    }
+
+    #[test]
+    fn undetermined_propagates() -> Result<()> {
+        let rules = vec!["A => B | C", "B => D", "C + E => F"];
+        assert_eq!(resolve(rules.clone(), "A", "DF")?, "?0");
+        assert_eq!(resolve(rules, "AE", "DF")?, "??");
+        Ok(())
+    }
+
+    #[test]
+    fn undetermined_resolved_by_other_rule() -> Result<()> {
+        let rules = vec!["A => B | C", "D => !B"];
+        assert_eq!(resolve(rules.clone(), "A", "BC")?, "??");
+        assert_eq!(resolve(rules.clone(), "AD", "BC")?, "01");
+        assert_eq!(resolve(rules, "AD", "CB")?, "10");
+        Ok(())
+    }
+
+    #[test]
+    fn example_input() -> Result<()> {
+        let rules = vec![
+            "C => E",
+            "A + B + C => D",
+            "A | B => C",
+            "A + !B => F",
+            "C | !G => H",
+            "V ^ W => X",
+            "A + B => Y + Z",
+            "C | D => X | V",
+            "E + F => !V",
+        ];
+        assert_eq!(resolve(rules.clone(), "ABG", "GVX")?, "1??");
+        assert_eq!(resolve(rules, "A", "CDEFHVXYZ")?, "101110100");
+        Ok(())
+    }
+
+    #[test]
+    fn long_identifiers() -> Result<()> {
+        let map = RuleMap::try_from(vec![
+            "HasFever + Cough => flu",
+            "flu | sensor.3.ok => !engine_overheat",
+        ])?;
+        let mut resolver = Resolver::new(&map, ["HasFever", "Cough"]);
+        assert_eq!(resolver.resolve("flu")?, Truth::True);
+        assert_eq!(resolver.resolve("engine_overheat")?, Truth::False);
+        assert_eq!(resolver.resolve("sensor.3.ok")?, Truth::False);
+        Ok(())
+    }
+
+    fn contradiction(rules: Vec<&str>, facts: &str) -> Option<String> {
+        let map = RuleMap::try_from(rules).unwrap();
+        let mut resolver = Resolver::new(&map, facts.chars());
+        resolver.check().err().map(|e| match e {
+            ExpertSystemError::Contradiction(c) => c.to_string(),
+            e => panic!("unexpected error: {}", e),
+        })
+    }
+
+    #[test]
+    fn check_consistent() {
+        assert_eq!(contradiction(vec!["A => B", "B => C"], "A"), None);
+        assert_eq!(contradiction(vec!["A => B | C", "D => !B"], "AD"), None);
+    }
+
+    #[test]
+    fn contradicting_rules() {
+        let rules = vec!["A => C", "B => !C"];
+        assert_eq!(
+            contradiction(rules.clone(), "AB"),
+            Some("Contradiction in 'A => C' and 'B => !C' when A is true, B is true".to_string())
+        );
+        assert_eq!(contradiction(rules, "A"), None);
+    }
+
+    #[test]
+    fn contradicting_rules_without_facts() {
+        let rules = vec!["A + B <=> C", "A + B <=> !C"];
+        assert!(contradiction(rules.clone(), "").is_some());
+        assert!(contradiction(rules, "AB").is_some());
+    }
+
+    #[test]
+    fn contradicting_fact() {
+        assert_eq!(
+            contradiction(vec!["A => !B"], "AB"),
+            Some("Contradiction in 'A => !B' when A is true, B is true".to_string())
+        );
+    }
+
+    #[test]
+    fn contradicting_conclusions() {
+        assert_eq!(
+            contradiction(vec!["A => B + C", "D => !B | !C"], "AD"),
+            Some(
+                "Contradiction in 'A => B + C' and 'D => !B | !C' when A is true, B is true, D is true"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn unsatisfiable_rule() {
+        assert_eq!(
+            contradiction(vec!["A + B => 0"], "AB"),
+            Some("Contradiction in 'A + B => 0' when A is true, B is true".to_string())
+        );
+        assert_eq!(contradiction(vec!["A + B => 0"], "A"), None);
+    }
+
+    #[test]
+    fn unsatisfiable_rule_without_identifiers() {
+        assert_eq!(
+            contradiction(vec!["1 => 0", "A => B"], "A"),
+            Some("Contradiction in '1 => 0'".to_string())
+        );
+        assert_eq!(contradiction(vec!["0 => 1", "A => B"], "A"), None);
+    }
+
+    #[test]
+    fn contradiction_on_query() {
+        let map = RuleMap::try_from(vec!["A => C", "B => !C"]).unwrap();
+        let mut resolver = Resolver::new(&map, "AB".chars());
+        assert!(resolver.resolve("A").is_ok());
+        let result = resolver.resolve("C");
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::Contradiction(Contradiction { rules, .. }))
+                if rules == vec!["A => C", "B => !C"]
+        ));
+    }
+
+    #[test]
+    fn tables() -> Result<()> {
+        let map = RuleMap::try_from(vec!["B => C", "A => C", "C => D", "C <=> E"])?;
+        let resolver = Resolver::new(&map, "".chars());
+        let rules: Vec<&str> = resolver
+            .tables("C")
+            .iter()
+            .map(|t| t.rule.as_str())
+            .collect();
+        assert_eq!(rules, vec!["A => C", "B => C", "C <=> E"]);
+        Ok(())
+    }
+
+    #[test]
+    fn explain() -> Result<()> {
+        let map = RuleMap::try_from(vec!["A | B => C", "C => E", "E + F => !V", "D => F"])?;
+        let mut resolver = Resolver::new(&map, "AD".chars());
+        assert_eq!(
+            resolver.explain("V")?.to_string(),
+            indoc! {"
+                V is false because E + F => !V
+                  E is true because C => E
+                    C is true because A | B => C
+                      A is true because it is a fact
+                      B is false because no rule determines it
+                  F is true because D => F
+                    D is true because it is a fact
+            "}
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn explain_cycle() -> Result<()> {
+        let map = RuleMap::try_from(vec!["A => B | C", "B => D"])?;
+        let mut resolver = Resolver::new(&map, "A".chars());
+        assert_eq!(
+            resolver.explain("D")?.to_string(),
+            indoc! {"
+                D is undetermined because B => D
+                  B is undetermined because A => B | C
+                    A is true because it is a fact
+                    C is undetermined because A => B | C
+                      A is true because it is a fact
+                      B is undetermined, see above
+            "}
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn error_depth() -> Result<()> {
+        let rules = vec!["A => B", "B => C", "C => D", "D => E"];
+        let limits = Limits {
+            depth: 3,
+            ..Limits::default()
+        };
+        let mut map = RuleMap::with_limits(Grammar::default(), limits);
+        map.insert_vec(rules)?;
+        assert_eq!(Resolver::new(&map, "A".chars()).resolve("D")?, Truth::True);
+        let result = Resolver::new(&map, "A".chars()).resolve("E");
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::Depth,
+                max: 3,
+                actual: 4,
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn error_timeout() -> Result<()> {
+        let limits = Limits {
+            timeout: Some(Duration::ZERO),
+            ..Limits::default()
+        };
+        let mut map = RuleMap::with_limits(Grammar::default(), limits);
+        map.insert("A => B")?;
+        let result = Resolver::new(&map, "A".chars()).resolve("B");
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::Timeout,
+                max: 0,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    // Threads sharing one map get the same results as resolving on a single thread
+    #[test]
+    fn parallel() -> Result<()> {
+        let rules: Vec<String> = (0..50)
+            .map(|i| format!("X{} | Y{} => X{}", i, i, i + 1))
+            .chain((0..50).map(|i| format!("X{} + Z{} => !Y{}", i, i, i + 1)))
+            .collect();
+        let map = RuleMap::try_from(rules)?;
+        let solve = |facts: Vec<String>| -> Result<Vec<Truth>, ExpertSystemError> {
+            let mut resolver = Resolver::new(&map, facts);
+            (0..=50)
+                .map(|i| resolver.resolve(&format!("X{}", i)))
+                .collect()
+        };
+        let facts = |seed: usize| -> Vec<String> {
+            (0..50)
+                .filter(|i| (seed >> (i % 8)) & 1 == 1)
+                .map(|i| format!("{}{}", ["Y", "Z"][i % 2], i))
+                .collect()
+        };
+        let expected: Vec<Vec<Truth>> = (0..128)
+            .map(|seed| solve(facts(seed)))
+            .collect::<Result<_, _>>()?;
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..128)
+                            .map(|seed| solve(facts(seed)))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                })
+                .collect();
+            for worker in workers {
+                assert_eq!(worker.join().unwrap().unwrap(), expected);
+            }
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn reuse_after_error() -> Result<()> {
+        // X is left in a cycle through Y when its contradiction is found
+        let map = RuleMap::try_from(vec!["Y => X", "X => Y", "A => X", "B => !X"])?;
+        let mut resolver = Resolver::new(&map, "AB".chars());
+        assert!(resolver.resolve("X").is_err());
+        assert_eq!(resolver.low_link, usize::MAX);
+        Ok(())
+    }
+}
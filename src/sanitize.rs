@@ -1,20 +1,46 @@
+use crate::*;
+
 use std::borrow::Borrow;
 
-fn remove_spaces(line: impl Borrow<str>) -> String {
-    line.borrow().split_whitespace().collect()
+// Removes all whitespace, except between two identifiers where a single space is kept to
+// tell them apart. Also returns the column in `line` of every character that is kept, an
+// inserted space takes the column of the first whitespace it replaces.
+fn remove_spaces(line: &str) -> (String, Vec<usize>) {
+    let mut result = String::new();
+    let mut columns = Vec::new();
+    let mut space = None;
+    for (column, c) in line.chars().enumerate() {
+        if c.is_whitespace() {
+            space = space.or(Some(column));
+            continue;
+        }
+        if let Some(space) = space.take() {
+            if result.ends_with(is_identifier_char) && is_identifier_char(c) {
+                result.push(' ');
+                columns.push(space);
+            }
+        }
+        result.push(c);
+        columns.push(column);
+    }
+    (result, columns)
 }
 
 fn remove_comment(line: impl Borrow<str>) -> String {
     line.borrow().split_terminator('#').take(1).collect()
 }
 
+// Removes the comment and whitespace of a single line, returns the result along with the
+// column in the original line of each of its characters.
+pub fn sanitize_line(line: &str) -> (String, Vec<usize>) {
+    remove_spaces(&remove_comment(line))
+}
+
 pub fn sanitize_lines(lines: &[impl Borrow<str>]) -> Vec<String> {
     let mut result: Vec<String> = Vec::new();
     let mut prev = String::new();
     for line in lines.iter() {
-        let mut sanitized = line.borrow().to_string();
-        sanitized = remove_comment(sanitized);
-        sanitized = remove_spaces(sanitized);
+        let (sanitized, _) = sanitize_line(line.borrow());
         if !result.is_empty() && prev.is_empty() && !sanitized.is_empty() {
             result.push(String::new());
         }
@@ -36,22 +62,53 @@ mod tests_remove_spaces {
     #[test]
     fn only_whitespace() {
         let input = " \t\n\r";
-        let result: String = remove_spaces(input);
+        let (result, _) = remove_spaces(input);
         assert_eq!(result, "");
     }
 
     #[test]
     fn no_whitespace() {
         let input = "hello-world";
-        let result: String = remove_spaces(input);
+        let (result, _) = remove_spaces(input);
         assert_eq!(result, input);
     }
 
     #[test]
     fn mixed() {
         let input = "  h e l l o\nw o r l d  ";
-        let result: String = remove_spaces(input);
-        assert_eq!(result, "helloworld");
+        let (result, _) = remove_spaces(input);
+        assert_eq!(result, "h e l l o w o r l d");
+    }
+
+    #[test]
+    fn operators() {
+        let input = "  ! ( A +\tB )\n=> C  ";
+        let (result, _) = remove_spaces(input);
+        assert_eq!(result, "!(A+B)=>C");
+    }
+
+    #[test]
+    fn between_identifiers() {
+        let input = " Has  Fever\tsensor.3.ok + A";
+        let (result, _) = remove_spaces(input);
+        assert_eq!(result, "Has Fever sensor.3.ok+A");
+    }
+}
+
+#[cfg(test)]
+mod tests_sanitize_line {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn columns() {
+        let (result, columns) = sanitize_line("  A +\tHas  Fever => C # comment");
+        assert_eq!(result, "A+Has Fever=>C");
+        assert_eq!(
+            columns,
+            vec![2, 4, 6, 7, 8, 9, 11, 12, 13, 14, 15, 17, 18, 20]
+        );
     }
 }
 
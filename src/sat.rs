@@ -0,0 +1,460 @@
+use std::fmt;
+use std::ops::Not;
+use std::time::Instant;
+
+// Literal of a solver variable, the lowest bit is set when the variable is negated
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn new(var: usize, negated: bool) -> Self {
+        Lit((var as u32) << 1 | negated as u32)
+    }
+
+    pub fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+impl fmt::Debug for Lit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", if self.negated() { "-" } else { "" }, self.var())
+    }
+}
+
+// Solver decides whether a set of clauses can be satisfied using conflict driven clause
+// learning. Each clause is watched by two of its literals, a clause only needs to be
+// visited when one of those becomes false. When propagation runs into a conflict the
+// solver learns the clause that rules out its cause (the first unique implication point)
+// and jumps back to the level where that clause forces a value. Variables are decided by
+// how often they took part in recent conflicts, using the value they last had.
+//
+// Learned clauses are kept between calls to `solve` since they follow from the added
+// clauses, which makes answering several queries on the same clauses cheap.
+#[derive(Default)]
+pub struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    // Clauses watching a literal, indexed by literal
+    watches: Vec<Vec<usize>>,
+    assigns: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    phase: Vec<bool>,
+    activity: Vec<f64>,
+    activity_inc: f64,
+    seen: Vec<bool>,
+    trail: Vec<Lit>,
+    // Length of the trail at the start of each decision level
+    trail_lim: Vec<usize>,
+    queue_head: usize,
+    // Set once the clauses can not be satisfied regardless of assumptions
+    unsatisfiable: bool,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Solver {
+            activity_inc: 1.0,
+            ..Solver::default()
+        }
+    }
+
+    pub fn new_var(&mut self) -> usize {
+        let var = self.assigns.len();
+        self.assigns.push(None);
+        self.level.push(0);
+        self.reason.push(None);
+        self.phase.push(false);
+        self.activity.push(0.0);
+        self.seen.push(false);
+        self.watches.push(Vec::new());
+        self.watches.push(Vec::new());
+        var
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.assigns.len()
+    }
+
+    // Returns the value of `lit` in the last satisfying assignment found by `solve`, or
+    // the value it has regardless of assumptions
+    pub fn value(&self, lit: Lit) -> Option<bool> {
+        self.assigns[lit.var()].map(|value| value != lit.negated())
+    }
+
+    // Adds a clause, returns false when the clauses can no longer be satisfied
+    pub fn add_clause(&mut self, lits: &[Lit]) -> bool {
+        self.backtrack(0);
+        if self.unsatisfiable {
+            return false;
+        }
+        let mut clause: Vec<Lit> = Vec::with_capacity(lits.len());
+        for &lit in lits {
+            match self.value(lit) {
+                Some(true) => return true,
+                Some(false) => {}
+                None if clause.contains(&!lit) => return true,
+                None if !clause.contains(&lit) => clause.push(lit),
+                None => {}
+            }
+        }
+        match clause.len() {
+            0 => self.unsatisfiable = true,
+            1 => {
+                self.assign(clause[0], None);
+                self.unsatisfiable = self.propagate().is_some();
+            }
+            _ => {
+                self.attach(clause);
+            }
+        }
+        !self.unsatisfiable
+    }
+
+    // Returns whether the clauses can be satisfied with every literal in `assumptions`
+    // true. The assignment that satisfies them can be read with `value` until the next
+    // call.
+    pub fn solve(&mut self, assumptions: &[Lit]) -> bool {
+        self.solve_until(assumptions, None)
+            .expect("solving without a deadline always finishes")
+    }
+
+    // Same as `solve`, but gives up and returns None once `deadline` has passed. The clock
+    // is only read after a conflict.
+    pub fn solve_until(&mut self, assumptions: &[Lit], deadline: Option<Instant>) -> Option<bool> {
+        self.backtrack(0);
+        if self.unsatisfiable {
+            return Some(false);
+        }
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.trail_lim.is_empty() {
+                    self.unsatisfiable = true;
+                    return Some(false);
+                }
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return None;
+                }
+                let (learnt, level) = self.analyze(conflict);
+                self.backtrack(level);
+                let asserting = learnt[0];
+                let reason = match learnt.len() {
+                    1 => None,
+                    _ => Some(self.attach(learnt)),
+                };
+                self.assign(asserting, reason);
+                self.activity_inc *= 1.05;
+                continue;
+            }
+            let lit = match assumptions.get(self.trail_lim.len()) {
+                Some(&lit) => match self.value(lit) {
+                    // Keeps one decision level per assumption
+                    Some(true) => {
+                        self.trail_lim.push(self.trail.len());
+                        continue;
+                    }
+                    Some(false) => return Some(false),
+                    None => lit,
+                },
+                None => match self.pick() {
+                    Some(lit) => lit,
+                    None => return Some(true),
+                },
+            };
+            self.trail_lim.push(self.trail.len());
+            self.assign(lit, None);
+        }
+    }
+
+    fn attach(&mut self, clause: Vec<Lit>) -> usize {
+        let index = self.clauses.len();
+        self.watches[clause[0].index()].push(index);
+        self.watches[clause[1].index()].push(index);
+        self.clauses.push(clause);
+        index
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.assigns[var] = Some(!lit.negated());
+        self.level[var] = self.trail_lim.len();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.trail_lim.len() <= level {
+            return;
+        }
+        for lit in self.trail.drain(self.trail_lim[level]..) {
+            self.phase[lit.var()] = !lit.negated();
+            self.assigns[lit.var()] = None;
+            self.reason[lit.var()] = None;
+        }
+        self.trail_lim.truncate(level);
+        self.queue_head = self.trail.len();
+    }
+
+    // Returns the unassigned variable with the highest activity, in its saved phase
+    fn pick(&self) -> Option<Lit> {
+        let mut best: Option<usize> = None;
+        for var in (0..self.num_vars()).filter(|v| self.assigns[*v].is_none()) {
+            if best.is_none_or(|b| self.activity[var] > self.activity[b]) {
+                best = Some(var);
+            }
+        }
+        best.map(|var| Lit::new(var, !self.phase[var]))
+    }
+
+    // Assigns the literals implied by the trail, returns a clause that became false
+    fn propagate(&mut self) -> Option<usize> {
+        while self.queue_head < self.trail.len() {
+            let false_lit = !self.trail[self.queue_head];
+            self.queue_head += 1;
+            let mut watching = std::mem::take(&mut self.watches[false_lit.index()]);
+            let mut i = 0;
+            let mut conflict = None;
+            while i < watching.len() {
+                let index = watching[i];
+                let clause = &mut self.clauses[index];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                let value = |lit: Lit| self.assigns[lit.var()].map(|v| v != lit.negated());
+                if value(first) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+                match (2..clause.len()).find(|k| value(clause[*k]) != Some(false)) {
+                    Some(k) => {
+                        clause.swap(1, k);
+                        let watch = clause[1];
+                        self.watches[watch.index()].push(index);
+                        watching.swap_remove(i);
+                    }
+                    None if value(first) == Some(false) => {
+                        conflict = Some(index);
+                        break;
+                    }
+                    None => {
+                        self.assign(first, Some(index));
+                        i += 1;
+                    }
+                }
+            }
+            self.watches[false_lit.index()] = watching;
+            if conflict.is_some() {
+                self.queue_head = self.trail.len();
+                return conflict;
+            }
+        }
+        None
+    }
+
+    // Learns a clause from `conflict` that has a single literal of the current level, the
+    // first literal of the returned clause. Returns it with the level to jump back to.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let current = self.trail_lim.len();
+        let mut learnt: Vec<Lit> = vec![Lit(0)];
+        let mut open = 0;
+        let mut index = self.trail.len();
+        let mut clause = conflict;
+        let mut implied: Option<Lit> = None;
+        loop {
+            let skip = implied.is_some() as usize;
+            for k in skip..self.clauses[clause].len() {
+                let lit = self.clauses[clause][k];
+                let var = lit.var();
+                if self.seen[var] || self.level[var] == 0 {
+                    continue;
+                }
+                self.seen[var] = true;
+                self.bump(var);
+                if self.level[var] == current {
+                    open += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+            let lit = loop {
+                index -= 1;
+                if self.seen[self.trail[index].var()] {
+                    break self.trail[index];
+                }
+            };
+            self.seen[lit.var()] = false;
+            implied = Some(lit);
+            open -= 1;
+            if open == 0 {
+                break;
+            }
+            clause = self.reason[lit.var()].expect("implied literal has a reason");
+        }
+        learnt[0] = !implied.expect("conflict has a literal of the current level");
+
+        let mut level = 0;
+        for k in 1..learnt.len() {
+            self.seen[learnt[k].var()] = false;
+            if self.level[learnt[k].var()] > level {
+                level = self.level[learnt[k].var()];
+                learnt.swap(1, k);
+            }
+        }
+        (learnt, level)
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.activity_inc;
+        if self.activity[var] > 1e100 {
+            self.activity.iter_mut().for_each(|a| *a *= 1e-100);
+            self.activity_inc *= 1e-100;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_solver {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    // Clauses are written as in DIMACS, variable `n` is literal `n` and `-n` its negation
+    fn solver(vars: usize, clauses: &[&[i32]]) -> Solver {
+        let mut solver = Solver::new();
+        for _ in 0..vars {
+            solver.new_var();
+        }
+        for clause in clauses {
+            let lits: Vec<Lit> = clause.iter().map(|l| lit(*l)).collect();
+            solver.add_clause(&lits);
+        }
+        solver
+    }
+
+    fn lit(l: i32) -> Lit {
+        Lit::new(l.unsigned_abs() as usize - 1, l < 0)
+    }
+
+    fn satisfies(solver: &Solver, clauses: &[&[i32]]) -> bool {
+        clauses
+            .iter()
+            .all(|c| c.iter().any(|l| solver.value(lit(*l)) == Some(true)))
+    }
+
+    #[test]
+    fn literals() {
+        let result = Lit::new(3, true);
+        assert_eq!(result.var(), 3);
+        assert!(result.negated());
+        assert_eq!(!result, Lit::new(3, false));
+        assert_eq!(format!("{:?}", result), "-3");
+    }
+
+    #[test]
+    fn satisfiable() {
+        let clauses: &[&[i32]] = &[&[1, 2], &[-1, 3], &[-2, -3], &[2, 3]];
+        let mut result = solver(3, clauses);
+        assert!(result.solve(&[]));
+        assert!(satisfies(&result, clauses));
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        let clauses: &[&[i32]] = &[&[1, 2], &[-1, 2], &[1, -2], &[-1, -2]];
+        let mut result = solver(2, clauses);
+        assert!(!result.solve(&[]));
+        assert!(!result.add_clause(&[lit(1)]));
+    }
+
+    #[test]
+    fn assumptions() {
+        let clauses: &[&[i32]] = &[&[-1, 2], &[-2, 3]];
+        let mut result = solver(3, clauses);
+        assert!(result.solve(&[lit(1)]));
+        assert_eq!(result.value(lit(3)), Some(true));
+        assert!(!result.solve(&[lit(1), lit(-3)]));
+        assert!(result.solve(&[lit(-3)]));
+        assert_eq!(result.value(lit(1)), Some(false));
+    }
+
+    #[test]
+    fn pigeon_hole() {
+        // 5 pigeons do not fit in 4 holes, variable 4 * p + h + 1 puts pigeon p in hole h
+        let var = |p: i32, h: i32| 4 * p + h + 1;
+        let mut clauses: Vec<Vec<i32>> = (0..5)
+            .map(|p| (0..4).map(|h| var(p, h)).collect())
+            .collect();
+        for h in 0..4 {
+            for p in 0..5 {
+                for q in p + 1..5 {
+                    clauses.push(vec![-var(p, h), -var(q, h)]);
+                }
+            }
+        }
+        let clauses: Vec<&[i32]> = clauses.iter().map(|c| c.as_slice()).collect();
+        assert!(!solver(20, &clauses).solve(&[]));
+        assert!(solver(20, &clauses[1..]).solve(&[]));
+
+        // Gives up at the first conflict once the deadline has passed
+        let deadline = Some(Instant::now());
+        assert_eq!(solver(20, &clauses).solve_until(&[], deadline), None);
+        assert_eq!(solver(20, &clauses[1..]).solve_until(&[], None), Some(true));
+    }
+
+    #[test]
+    fn random_3_sat() {
+        // Compares against trying every assignment of a small number of variables
+        let mut seed: u64 = 7;
+        let mut next = |n: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+        for _ in 0..200 {
+            let clauses: Vec<Vec<i32>> = (0..40)
+                .map(|_| {
+                    (0..3)
+                        .map(|_| {
+                            let v = next(10) as i32 + 1;
+                            if next(2) == 0 {
+                                v
+                            } else {
+                                -v
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+            let clauses: Vec<&[i32]> = clauses.iter().map(|c| c.as_slice()).collect();
+            let expected = (0..1 << 10).any(|row: u32| {
+                clauses.iter().all(|c| {
+                    c.iter()
+                        .any(|l| (row >> (l.unsigned_abs() - 1) & 1 == 1) == (*l > 0))
+                })
+            });
+            let mut result = solver(10, &clauses);
+            assert_eq!(result.solve(&[]), expected);
+            if expected {
+                assert!(satisfies(&result, &clauses));
+            }
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use crate::*;
+use error::ExpertSystemError;
+use expr::Expr;
+use limits::Limits;
+use parser::{RuleMap, Truth, TruthTable};
+use resolver::Contradiction;
+use sat::{Lit, Solver};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Instant;
+
+// SatResolver answers queries by checking satisfiability instead of combining truth tables,
+// which keeps large rule sets tractable. Every rule is encoded into clauses with the Tseitin
+// encoding: each operator in the expression of the rule gets a solver variable that is
+// defined to equal the operator applied to its operands, so the clauses grow linearly with
+// the rule. A query is true when the rules and facts can not be satisfied with it false,
+// false when they can not be satisfied with it true and undetermined when both are possible.
+//
+// Identifiers that are not a fact and can not be derived from any rule are assumed false,
+// every other identifier that is not a fact gets a completion clause: it can only be true
+// when the premise of one of the rules deriving it holds. With only `A` as fact, `B` is false
+// for `A + B => C` and `C => D`, so the premise of the first rule does not hold and `C` and
+// `D` are false as well.
+//
+// The completion does not rule out identifiers that only support each other through a cycle
+// of rules. Without facts, `A => B` and `B => A` are satisfied both with `A` and `B` true and
+// with both false, so they are undetermined here while the other engines make them false.
+//
+// Resolving fails once the timeout of the limits of the map has passed since the resolver
+// was created.
+pub struct SatResolver<'a> {
+    solver: Solver,
+    variables: HashMap<String, usize>,
+    // Every rule is only enforced while its selector is assumed true, this allows finding
+    // the rules that contradict each other.
+    selectors: Vec<(Lit, &'a TruthTable)>,
+    // Facts and identifiers that are false since they can not be derived
+    assumptions: Vec<(String, Lit)>,
+    resolved: HashMap<String, Truth>,
+    consistent: bool,
+    limits: Limits,
+    start: Instant,
+}
+
+impl<'a> SatResolver<'a> {
+    pub fn new<I>(map: &'a RuleMap, facts: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let facts: HashSet<String> = facts.into_iter().map(Into::into).collect();
+        let mut tables: Vec<&'a TruthTable> =
+            map.truth_tables().into_iter().map(|t| t.as_ref()).collect();
+        tables.sort_unstable_by(|a, b| a.rule.cmp(&b.rule));
+        let mut identifiers: Vec<&String> = map.identifiers().chain(facts.iter()).collect();
+        identifiers.sort_unstable();
+        identifiers.dedup();
+
+        let mut solver = Solver::new();
+        let mut variables = HashMap::new();
+        let mut assumptions = Vec::new();
+        for ident in identifiers {
+            let var = solver.new_var();
+            variables.insert(ident.clone(), var);
+            if facts.contains(ident) {
+                assumptions.push((ident.clone(), Lit::new(var, false)));
+            } else if tables.iter().all(|t| t.sources(ident).is_none()) {
+                assumptions.push((ident.clone(), Lit::new(var, true)));
+            }
+        }
+        let mut resolver = SatResolver {
+            solver,
+            variables,
+            selectors: Vec::new(),
+            assumptions,
+            resolved: HashMap::new(),
+            consistent: false,
+            limits: *map.limits(),
+            start: Instant::now(),
+        };
+        // Premises of the rules that can derive each identifier
+        let mut premises: BTreeMap<&String, Vec<Lit>> = BTreeMap::new();
+        for table in tables {
+            let vars: Vec<usize> = table
+                .variables
+                .iter()
+                .map(|v| resolver.variables[v])
+                .collect();
+            let (lhs, rhs) = match &table.expr {
+                Expr::Imply(l, r) | Expr::Iff(l, r) => {
+                    (resolver.encode(l, &vars), resolver.encode(r, &vars))
+                }
+                _ => unreachable!("a parsed rule always has an implicator"),
+            };
+            let root = resolver.gate(&table.expr, lhs, rhs);
+            let selector = Lit::new(resolver.solver.new_var(), false);
+            resolver.solver.add_clause(&[!selector, root]);
+            resolver.selectors.push((selector, table));
+            for ident in table.variables.iter().filter(|v| !facts.contains(*v)) {
+                if table.sources(ident).is_some() {
+                    let premise = match table.consequent.contains(ident) {
+                        true => lhs,
+                        false => rhs,
+                    };
+                    premises.entry(ident).or_default().push(premise);
+                }
+            }
+        }
+        for (ident, premises) in premises {
+            let var = resolver.variables[ident];
+            let clause: Vec<Lit> = std::iter::once(Lit::new(var, true))
+                .chain(premises)
+                .collect();
+            resolver.solver.add_clause(&clause);
+        }
+        resolver
+    }
+
+    // Verifies that the rules and facts can all hold at the same time
+    pub fn check(&mut self) -> Result<(), ExpertSystemError> {
+        if self.consistent {
+            return Ok(());
+        }
+        let mut selected: Vec<(Lit, &'a TruthTable)> = self.selectors.clone();
+        if self.satisfiable(&selected, &[])? {
+            self.consistent = true;
+            return Ok(());
+        }
+        // Removes every rule that is not needed for the contradiction
+        let mut i = 0;
+        while i < selected.len() {
+            let removed = selected.remove(i);
+            if !self.satisfiable(&selected, &[])? {
+                continue;
+            }
+            selected.insert(i, removed);
+            i += 1;
+        }
+
+        let mut assignment: Vec<(String, bool)> = self
+            .assumptions
+            .iter()
+            .filter(|(ident, _)| selected.iter().any(|(_, t)| t.variables.contains(ident)))
+            .map(|(ident, lit)| (ident.clone(), !lit.negated()))
+            .collect();
+        assignment.sort_unstable();
+        Err(Contradiction {
+            rules: selected.iter().map(|(_, t)| t.rule.clone()).collect(),
+            assignment,
+        }
+        .into())
+    }
+
+    pub fn resolve(&mut self, ident: &str) -> Result<Truth, ExpertSystemError> {
+        self.check()?;
+        if let Some(value) = self.resolved.get(ident) {
+            return Ok(*value);
+        }
+        let value = match self.variables.get(ident) {
+            Some(var) => {
+                let selected = self.selectors.clone();
+                let query = Lit::new(*var, false);
+                match (
+                    self.satisfiable(&selected, &[query])?,
+                    self.satisfiable(&selected, &[!query])?,
+                ) {
+                    (true, true) => Truth::Undetermined,
+                    (true, false) => Truth::True,
+                    (false, true) => Truth::False,
+                    (false, false) => unreachable!("the rules and facts are consistent"),
+                }
+            }
+            None => Truth::False,
+        };
+        self.resolved.insert(ident.to_string(), value);
+        Ok(value)
+    }
+
+    fn satisfiable(
+        &mut self,
+        selected: &[(Lit, &TruthTable)],
+        extra: &[Lit],
+    ) -> Result<bool, ExpertSystemError> {
+        self.limits.check_timeout(self.start)?;
+        let mut assumptions: Vec<Lit> = self.assumptions.iter().map(|(_, lit)| *lit).collect();
+        assumptions.extend(selected.iter().map(|(selector, _)| *selector));
+        assumptions.extend(extra);
+        let deadline = self.limits.timeout.map(|timeout| self.start + timeout);
+        match self.solver.solve_until(&assumptions, deadline) {
+            Some(satisfiable) => Ok(satisfiable),
+            None => {
+                self.limits.check_timeout(self.start)?;
+                unreachable!("the solver only gives up once the timeout has passed")
+            }
+        }
+    }
+
+    // Returns a literal that is true exactly when `expr` is, `vars` are the solver
+    // variables of the variables of the expression.
+    fn encode(&mut self, expr: &Expr, vars: &[usize]) -> Lit {
+        let (a, b) = match expr {
+            Expr::Bool(value) => {
+                let lit = Lit::new(self.solver.new_var(), false);
+                self.solver.add_clause(&[lit]);
+                return if *value { lit } else { !lit };
+            }
+            Expr::Var(i) => return Lit::new(vars[*i], false),
+            Expr::Not(e) => return !self.encode(e, vars),
+            Expr::And(l, r)
+            | Expr::Or(l, r)
+            | Expr::Xor(l, r)
+            | Expr::Imply(l, r)
+            | Expr::Iff(l, r) => (self.encode(l, vars), self.encode(r, vars)),
+        };
+        self.gate(expr, a, b)
+    }
+
+    // Returns a literal that is true exactly when the binary operator of `expr` applied to
+    // `a` and `b` is
+    fn gate(&mut self, expr: &Expr, a: Lit, b: Lit) -> Lit {
+        let x = Lit::new(self.solver.new_var(), false);
+        let clauses: Vec<[Lit; 3]> = match expr {
+            Expr::And(..) => vec![[!x, a, a], [!x, b, b], [x, !a, !b]],
+            Expr::Or(..) => vec![[x, !a, !a], [x, !b, !b], [!x, a, b]],
+            Expr::Imply(..) => vec![[x, a, a], [x, !b, !b], [!x, !a, b]],
+            Expr::Xor(..) => vec![[!x, a, b], [!x, !a, !b], [x, !a, b], [x, a, !b]],
+            _ => vec![[x, a, b], [x, !a, !b], [!x, !a, b], [!x, a, !b]],
+        };
+        for clause in clauses {
+            self.solver.add_clause(&clause);
+        }
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests_sat_resolver {
+    use super::*;
+    use error::Limit;
+    use parser::Grammar;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn resolve(rules: Vec<&str>, facts: &str, queries: &str) -> Result<String> {
+        let map = RuleMap::try_from(rules)?;
+        let mut resolver = SatResolver::new(&map, facts.chars());
+        let mut result = String::new();
+        for query in queries.chars() {
+            result.push(match resolver.resolve(&query.to_string())? {
+                Truth::True => '1',
+                Truth::False => '0',
+                Truth::Undetermined => '?',
+            });
+        }
+        Ok(result)
+    }
+
+    #[test]
+    fn operators() -> Result<()> {
+        assert_eq!(resolve(vec!["A + B => C"], "AB", "C")?, "1");
+        assert_eq!(resolve(vec!["A + B => C"], "A", "BC")?, "00");
+        assert_eq!(resolve(vec!["A | B => C"], "B", "C")?, "1");
+        assert_eq!(resolve(vec!["A ^ B => C"], "AB", "C")?, "0");
+        assert_eq!(resolve(vec!["A ^ B => C"], "B", "C")?, "1");
+        assert_eq!(resolve(vec!["!A => C"], "", "AC")?, "01");
+        assert_eq!(resolve(vec!["A <=> C"], "A", "C")?, "1");
+        assert_eq!(resolve(vec!["A => !C", "B => C"], "A", "C")?, "0");
+        assert_eq!(resolve(vec!["A => B | C"], "A", "BC")?, "??");
+        assert_eq!(resolve(vec!["1 => C", "0 | A => D"], "", "CD")?, "10");
+        Ok(())
+    }
+
+    #[test]
+    fn combined_rules() -> Result<()> {
+        // Only follows from both rules together, backward chaining leaves B undetermined
+        assert_eq!(resolve(vec!["A => B | C", "A => B | !C"], "A", "B")?, "1");
+        Ok(())
+    }
+
+    #[test]
+    fn false_by_default() -> Result<()> {
+        // C can only be derived when both A and B hold, so it is false as in the other modes
+        assert_eq!(resolve(vec!["A + B => C", "C => D"], "A", "BCD")?, "000");
+        assert_eq!(resolve(vec!["A + B => C", "C => D"], "AB", "BCD")?, "111");
+        // Only one of the rules deriving B has to hold
+        assert_eq!(resolve(vec!["A => B", "C => B"], "C", "AB")?, "01");
+        assert_eq!(resolve(vec!["A => B | C"], "A", "BC")?, "??");
+        Ok(())
+    }
+
+    #[test]
+    fn cycle() -> Result<()> {
+        // Only the cycle supports A and B, unlike the other engines they are not false
+        assert_eq!(resolve(vec!["A => B", "B => A"], "", "AB")?, "??");
+        assert_eq!(
+            resolve(vec!["A => B", "B => A", "C => A"], "C", "AB")?,
+            "11"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn long_chain() -> Result<()> {
+        // Hundreds of identifiers, the rules on their own would combine into a table with
+        // 2^600 rows
+        let mut rules: Vec<String> = (0..300)
+            .map(|i| format!("X{} + Y{} => X{}", i, i, i + 1))
+            .collect();
+        rules.push("X300 => Z".to_string());
+        let map = RuleMap::try_from(rules)?;
+        let facts: Vec<String> = std::iter::once("X0".to_string())
+            .chain((0..300).map(|i| format!("Y{}", i)))
+            .collect();
+        let mut resolver = SatResolver::new(&map, &facts);
+        assert_eq!(resolver.resolve("Z")?, Truth::True);
+
+        let mut resolver = SatResolver::new(&map, &facts[..300]);
+        assert_eq!(resolver.resolve("Z")?, Truth::False);
+        assert_eq!(resolver.resolve("Y299")?, Truth::False);
+        Ok(())
+    }
+
+    #[test]
+    fn error_timeout() -> Result<()> {
+        let limits = Limits {
+            timeout: Some(Duration::ZERO),
+            ..Limits::default()
+        };
+        let mut map = RuleMap::with_limits(Grammar::default(), limits);
+        map.insert("A => B")?;
+        let result = SatResolver::new(&map, "A".chars()).resolve("B");
+        assert!(matches!(
+            result,
+            Err(ExpertSystemError::LimitExceeded {
+                limit: Limit::Timeout,
+                max: 0,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn contradiction() {
+        let map = RuleMap::try_from(vec!["A => C", "B => !C", "D => E"]).unwrap();
+        let mut resolver = SatResolver::new(&map, "ABD".chars());
+        assert_eq!(
+            resolver.resolve("E").unwrap_err().to_string(),
+            "Contradiction in 'A => C' and 'B => !C' when A is true, B is true"
+        );
+        assert!(SatResolver::new(&map, "A".chars()).check().is_ok());
+
+        let map = RuleMap::try_from(vec!["1 => 0", "A => B"]).unwrap();
+        let mut resolver = SatResolver::new(&map, "A".chars());
+        assert_eq!(
+            resolver.resolve("A").unwrap_err().to_string(),
+            "Contradiction in '1 => 0'"
+        );
+    }
+}
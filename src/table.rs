@@ -0,0 +1,166 @@
+use crate::*;
+use parser::TruthTable;
+
+use std::fmt;
+
+// Format in which a truth table is rendered. Every format has a column per identifier and a
+// last column with the result of the rule.
+// `Markdown` is a table with a `=` column, as printed by the Debug impl of TruthTable.
+// `Csv` names the result column after the rule, since spreadsheets read a lone `=` as the
+// start of a formula. `Text` aligns the columns without any markup.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum TableFormat {
+    #[default]
+    Markdown,
+    Csv,
+    Text,
+}
+
+// Displays a truth table in a TableFormat, see TruthTable::render
+pub struct Rendered<'a> {
+    table: &'a TruthTable,
+    format: TableFormat,
+    satisfying: bool,
+}
+
+impl TruthTable {
+    // Renders the table in `format`, leaving out the rows in which the rule does not hold
+    // when `satisfying` is set
+    pub fn render(&self, format: TableFormat, satisfying: bool) -> Rendered<'_> {
+        Rendered {
+            table: self,
+            format,
+            satisfying,
+        }
+    }
+}
+
+impl Rendered<'_> {
+    // Returns the cells of every printed row, starting with the header
+    fn rows(&self) -> Vec<Vec<String>> {
+        let table = self.table;
+        let result = match self.format {
+            TableFormat::Csv | TableFormat::Text => table.rule.clone(),
+            TableFormat::Markdown => "=".to_string(),
+        };
+        let header = table.variables.iter().cloned().chain([result]).collect();
+        let bit = |value: bool| if value { "1" } else { "0" }.to_string();
+        let rows = table
+            .results
+            .iter()
+            .enumerate()
+            .filter(|(_, result)| **result || !self.satisfying)
+            .map(|(row, result)| {
+                (0..table.variables.len())
+                    .map(|i| bit(table.value(row, i)))
+                    .chain([bit(*result)])
+                    .collect()
+            });
+        [header].into_iter().chain(rows).collect()
+    }
+}
+
+impl fmt::Display for Rendered<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows = self.rows();
+        // Columns are as wide as their header
+        let widths: Vec<usize> = rows[0].iter().map(|cell| cell.len()).collect();
+        for (index, row) in rows.iter().enumerate() {
+            let cells = row.iter().zip(widths.iter());
+            match self.format {
+                TableFormat::Markdown => {
+                    for (cell, width) in cells {
+                        write!(f, "| {:<width$} ", cell, width = width)?;
+                    }
+                    writeln!(f, "|")?;
+                    if index == 0 {
+                        for width in widths.iter() {
+                            write!(f, "|-{}-", "-".repeat(*width))?;
+                        }
+                        writeln!(f, "|")?;
+                    }
+                }
+                TableFormat::Csv => writeln!(f, "{}", row.join(","))?,
+                TableFormat::Text => {
+                    let (result, values) = row.split_last().unwrap();
+                    for (cell, width) in values.iter().zip(widths.iter()) {
+                        write!(f, "{:<width$} ", cell, width = width)?;
+                    }
+                    match values.is_empty() {
+                        true => writeln!(f, "{}", result)?,
+                        false => writeln!(f, "| {}", result)?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_table {
+    use super::*;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn markdown() -> Result<()> {
+        let table = TruthTable::try_from("Ab => B")?;
+        assert_eq!(
+            table.render(TableFormat::Markdown, false).to_string(),
+            indoc! {"
+                | Ab | B | = |
+                |----|---|---|
+                | 0  | 0 | 1 |
+                | 0  | 1 | 1 |
+                | 1  | 0 | 0 |
+                | 1  | 1 | 1 |
+            "}
+        );
+        assert_eq!(
+            table.render(TableFormat::Markdown, false).to_string(),
+            format!("{:?}", table)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn csv() -> Result<()> {
+        let table = TruthTable::try_from("A + B => C")?;
+        assert_eq!(
+            table.render(TableFormat::Csv, true).to_string(),
+            indoc! {"
+                A,B,C,A + B => C
+                0,0,0,1
+                0,0,1,1
+                0,1,0,1
+                0,1,1,1
+                1,0,0,1
+                1,0,1,1
+                1,1,1,1
+            "}
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn text() -> Result<()> {
+        let table = TruthTable::try_from("HasFever => Flu")?;
+        assert_eq!(
+            table.render(TableFormat::Text, true).to_string(),
+            indoc! {"
+                Flu HasFever | HasFever => Flu
+                0   0        | 1
+                1   0        | 1
+                1   1        | 1
+            "}
+        );
+        let table = TruthTable::try_from("1 => 0")?;
+        assert_eq!(
+            table.render(TableFormat::Text, false).to_string(),
+            "1 => 0\n0\n"
+        );
+        Ok(())
+    }
+}
@@ -1,10 +1,11 @@
 extern crate expert_system;
-use expert_system::USAGE;
+use expert_system::{CONTRADICTION_EXIT_CODE, USAGE};
 
 mod test_utils;
 
 use assert_cmd::assert::*;
 use assert_cmd::cargo::CommandCargoExt;
+use indoc::indoc;
 use std::process::Command;
 
 macro_rules! _run_cmd {
@@ -41,17 +42,548 @@ fn white_space() {
 #[test]
 fn example_input() {
     let input_file = test_utils::input_file_path("integration_test/example_input.txt");
+    run_cmd!(input_file.display().to_string())
+        .failure()
+        .code(CONTRADICTION_EXIT_CODE);
+}
+
+#[test]
+fn consistent_input() {
+    let input_file = test_utils::input_file_path("integration_test/consistent_input.txt");
     run_cmd!(input_file.display().to_string()).success();
 }
 
+#[test]
+fn solve() {
+    let input_file = test_utils::input_file_path("integration_test/consistent_input.txt");
+    run_cmd!("solve".to_string(), input_file.display().to_string())
+        .success()
+        .stdout("G is true\nV is undetermined\nX is undetermined\n");
+}
+
+#[test]
+fn solve_forward() {
+    let input_file = test_utils::input_file_path("integration_test/consistent_input.txt");
+    run_cmd!(
+        "--mode".to_string(),
+        "forward".to_string(),
+        input_file.display().to_string()
+    )
+    .success()
+    .stdout("G is true\nV is undetermined\nX is undetermined\nDerived: C, D, E, H, Y, Z\n");
+}
+
+#[test]
+fn solve_sat() {
+    let input_file = test_utils::input_file_path("integration_test/consistent_input.txt");
+    // X follows from combining `V ^ W => X` and `C | D => X | V` with W false, which the
+    // other modes do not do
+    run_cmd!("--mode=sat".to_string(), input_file.display().to_string())
+        .success()
+        .stdout("G is true\nV is undetermined\nX is true\n");
+}
+
+#[test]
+fn solve_false_by_default() {
+    let input_file = test_utils::input_file_path("integration_test/false_by_default.txt");
+    for (mode, derived) in [
+        ("backward", ""),
+        ("forward", "Nothing derived\n"),
+        ("sat", ""),
+    ] {
+        run_cmd!(format!("--mode={}", mode), input_file.display().to_string())
+            .success()
+            .stdout(format!("C is false\nD is false\n{}", derived));
+    }
+}
+
+#[test]
+fn solve_cyclic_rules() {
+    // The rules only support A and B through each other, the SAT solver does not make them
+    // false by default since it can satisfy the rules either way
+    let input_file = test_utils::input_file_path("integration_test/cyclic_rules.txt");
+    for (mode, expected) in [
+        ("backward", "A is false\nB is false\n"),
+        ("forward", "A is false\nB is false\nNothing derived\n"),
+        ("sat", "A is undetermined\nB is undetermined\n"),
+    ] {
+        run_cmd!(format!("--mode={}", mode), input_file.display().to_string())
+            .success()
+            .stdout(expected);
+    }
+}
+
+#[test]
+fn solve_sat_contradiction() {
+    let input_file = test_utils::input_file_path("integration_test/example_input.txt");
+    run_cmd!("--mode=sat".to_string(), input_file.display().to_string())
+        .failure()
+        .code(CONTRADICTION_EXIT_CODE);
+}
+
+#[test]
+fn solve_forward_contradiction() {
+    let input_file = test_utils::input_file_path("integration_test/example_input.txt");
+    run_cmd!(
+        "--mode=forward".to_string(),
+        input_file.display().to_string()
+    )
+    .failure()
+    .code(CONTRADICTION_EXIT_CODE);
+}
+
+#[test]
+fn solve_long_identifiers() {
+    let input_file = test_utils::input_file_path("integration_test/long_identifiers.txt");
+    run_cmd!(input_file.display().to_string())
+        .success()
+        .stdout("flu is true\nengine_overheat is false\n");
+}
+
+#[test]
+fn solve_ambiguous_identifiers() {
+    // `AB` could be one identifier or `A` and `B` since the line also has `Cd`
+    let input_file = test_utils::input_file_path("integration_test/ambiguous_identifiers.txt");
+    run_cmd!(input_file.display().to_string())
+        .failure()
+        .code(1)
+        .stdout("");
+}
+
+#[test]
+fn solve_compact() {
+    let input_file = test_utils::input_file_path("integration_test/consistent_input.txt");
+    run_cmd!(
+        "--format".to_string(),
+        "compact".to_string(),
+        input_file.display().to_string()
+    )
+    .success()
+    .stdout("G=1 V=? X=?\n");
+}
+
+#[test]
+fn solve_json() {
+    let input_file = test_utils::input_file_path("integration_test/spacing.txt");
+    run_cmd!(
+        "--mode=forward".to_string(),
+        "--format=json".to_string(),
+        input_file.display().to_string()
+    )
+    .success()
+    .stdout(indoc! {r#"
+        {
+          "rules": [
+            "A=>Z"
+          ],
+          "scenarios": [
+            {
+              "facts": [
+                "A"
+              ],
+              "queries": [
+                {
+                  "query": "Z",
+                  "value": "true",
+                  "explanation": null
+                }
+              ],
+              "derived": [
+                "Z"
+              ]
+            }
+          ],
+          "warnings": []
+        }
+    "#});
+}
+
+#[test]
+fn solve_scenarios() {
+    let input_file = test_utils::input_file_path("integration_test/scenarios.txt");
+    run_cmd!(input_file.display().to_string())
+        .success()
+        .stdout(indoc! {"
+            Scenario 1: =A
+            C is true
+            E is false
+            Scenario 2: =BD
+            C is true
+            E is true
+            Scenario 3: =
+            C is false
+        "});
+    run_cmd!(
+        "--format=compact".to_string(),
+        input_file.display().to_string()
+    )
+    .success()
+    .stdout("C=1 E=0\nC=1 E=1\nC=0\n");
+}
+
+#[test]
+fn solve_include() {
+    let input_file = test_utils::input_file_path("input/include/main.txt");
+    run_cmd!(input_file.display().to_string())
+        .success()
+        .stdout("flu is true\nengine_overheat is false\n");
+
+    let input_file = test_utils::input_file_path("input/include/cycle_a.txt");
+    let assert = run_cmd!(input_file.display().to_string()).failure().code(1);
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert!(stderr.starts_with("Error: Include cycle"));
+    assert!(stderr.contains("cycle_b.txt:1:10"));
+}
+
+#[test]
+fn fmt() {
+    let input_file = test_utils::input_file_path("integration_test/consistent_input.txt");
+    let formatted = test_utils::input_file_path("integration_test/formatted.txt");
+    run_cmd!("fmt".to_string(), input_file.display().to_string())
+        .success()
+        .stdout(std::fs::read_to_string(&formatted).unwrap());
+}
+
+#[test]
+fn fmt_check() {
+    let formatted = test_utils::input_file_path("integration_test/formatted.txt");
+    run_cmd!(
+        "fmt".to_string(),
+        "--check".to_string(),
+        formatted.display().to_string()
+    )
+    .success()
+    .stdout("");
+
+    let input_file = test_utils::input_file_path("integration_test/consistent_input.txt");
+    let assert = run_cmd!(
+        "fmt".to_string(),
+        "--check".to_string(),
+        input_file.display().to_string()
+    )
+    .failure()
+    .code(1);
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert!(stderr.starts_with(&format!(
+        "Error: '{}' is not formatted",
+        input_file.display()
+    )));
+}
+
+#[test]
+fn check_warnings() {
+    let input_file = test_utils::input_file_path("integration_test/warnings.txt");
+    let assert = run_cmd!("check".to_string(), input_file.display().to_string())
+        .success()
+        .stdout("No contradictions found\n");
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert_eq!(
+        stderr,
+        format!(
+            indoc! {"
+                Warning: Rule 'A+B=>C' is the same as 'B+A=>C' on line 2
+                 --> {0}:3:3
+                  |
+                3 |   A + B => C # dup
+                  |   ^^^^^^^^^^
+                Warning: Rule 'A=>A' always holds
+                 --> {0}:4:1
+                  |
+                4 | A => A
+                  | ^^^^^^
+                Warning: Query 'Y' does not appear in any rule
+                Warning: Fact 'X' does not appear in any rule
+            "},
+            input_file.display()
+        )
+    );
+
+    run_cmd!(
+        "check".to_string(),
+        "--quiet".to_string(),
+        input_file.display().to_string()
+    )
+    .success()
+    .stderr("");
+}
+
+#[test]
+fn deny_warnings() {
+    let input_file = test_utils::input_file_path("integration_test/warnings.txt");
+    let assert = run_cmd!(
+        "--deny-warnings".to_string(),
+        input_file.display().to_string()
+    )
+    .failure()
+    .code(1)
+    .stdout("");
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert!(stderr.contains("Error: Found 4 warnings with --deny-warnings"));
+
+    let input_file = test_utils::input_file_path("integration_test/consistent_input.txt");
+    run_cmd!(
+        "check".to_string(),
+        "--deny-warnings".to_string(),
+        input_file.display().to_string()
+    )
+    .success();
+}
+
+#[test]
+fn solve_quiet() {
+    let input_file = test_utils::input_file_path("integration_test/consistent_input.txt");
+    run_cmd!("-q".to_string(), input_file.display().to_string())
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn solve_stdin() {
+    let input_file = test_utils::input_file_path("integration_test/spacing.txt");
+    assert_cmd::Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .args(["solve", "-"])
+        .write_stdin(std::fs::read_to_string(input_file).unwrap())
+        .assert()
+        .success()
+        .stdout("Z is true\n");
+}
+
+#[test]
+fn check() {
+    let input_file = test_utils::input_file_path("integration_test/consistent_input.txt");
+    run_cmd!("check".to_string(), input_file.display().to_string())
+        .success()
+        .stdout("No contradictions found\n");
+}
+
+#[test]
+fn check_contradiction() {
+    let input_file = test_utils::input_file_path("integration_test/example_input.txt");
+    run_cmd!("check".to_string(), input_file.display().to_string())
+        .failure()
+        .code(CONTRADICTION_EXIT_CODE);
+}
+
+#[test]
+fn check_contradiction_without_backtrace() {
+    // The error is printed as a single line even when backtraces are enabled
+    let input_file = test_utils::input_file_path("integration_test/example_input.txt");
+    assert_cmd::Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .args(["check".to_string(), input_file.display().to_string()])
+        .env("RUST_BACKTRACE", "1")
+        .assert()
+        .failure()
+        .code(CONTRADICTION_EXIT_CODE)
+        .stderr(
+            "Error: Inconsistent rules and facts: Contradiction in 'A+B<=>C' and 'A+B<=>!C' \
+             when A is true, B is true\n",
+        );
+}
+
+#[test]
+fn check_unsatisfiable_constant_rule() {
+    let input_file = test_utils::input_file_path("integration_test/constant_rule.txt");
+    for mode in ["backward", "forward", "sat"] {
+        run_cmd!(format!("--mode={}", mode), input_file.display().to_string())
+            .failure()
+            .code(CONTRADICTION_EXIT_CODE);
+        run_cmd!(
+            "check".to_string(),
+            format!("--mode={}", mode),
+            input_file.display().to_string()
+        )
+        .failure()
+        .code(CONTRADICTION_EXIT_CODE);
+    }
+}
+
+#[test]
+fn table() {
+    run_cmd!("table", "A => B").success().stdout(indoc! {"
+        | A | B | = |
+        |---|---|---|
+        | 0 | 0 | 1 |
+        | 0 | 1 | 1 |
+        | 1 | 0 | 0 |
+        | 1 | 1 | 1 |
+    "});
+}
+
+#[test]
+fn table_left_to_right() {
+    run_cmd!("--left-to-right", "table", "1 | 0 + 0 => 0")
+        .success()
+        .stdout(indoc! {"
+            | = |
+            |---|
+            | 1 |
+        "});
+    run_cmd!("table", "1 | 0 + 0 => 0")
+        .success()
+        .stdout(indoc! {"
+        | = |
+        |---|
+        | 0 |
+    "});
+}
+
+#[test]
+fn table_formats() {
+    run_cmd!("table", "A => B", "--format", "csv")
+        .success()
+        .stdout(indoc! {"
+            A,B,A => B
+            0,0,1
+            0,1,1
+            1,0,0
+            1,1,1
+        "});
+    run_cmd!("table", "A + B <=> C", "-f", "text", "--satisfying")
+        .success()
+        .stdout(indoc! {"
+            A B C | A + B <=> C
+            0 0 0 | 1
+            0 1 0 | 1
+            1 0 0 | 1
+            1 1 1 | 1
+        "});
+}
+
+#[test]
+fn normalize() {
+    run_cmd!("normalize", "--minimize", "A | B => X | V")
+        .success()
+        .stdout(indoc! {"
+            NNF: !A + !B | X | V
+            CNF: (!A | V | X) + (!B | V | X)
+            DNF: !A + !B | V | X
+        "});
+    run_cmd!("normalize", "--form=cnf", "A => B")
+        .success()
+        .stdout("!A | B\n");
+}
+
+#[test]
+fn normalize_minimize_too_many_variables() {
+    run_cmd!(
+        "normalize",
+        "--minimize",
+        "A ^ B ^ C ^ D ^ E ^ F ^ G ^ H ^ I ^ J ^ K ^ L => M"
+    )
+    .failure()
+    .stdout("");
+}
+
+#[test]
+fn normalize_left_to_right() {
+    // Printed so that it reads back with the grammar it was parsed with
+    run_cmd!(
+        "--left-to-right",
+        "normalize",
+        "--form=nnf",
+        "A | B + C => D"
+    )
+    .success()
+    .stdout("!A + !B | !C | D\n");
+    run_cmd!(
+        "--left-to-right",
+        "normalize",
+        "--form=cnf",
+        "--minimize",
+        "A | B => C + D"
+    )
+    .success()
+    .stdout("!A | C + (!A | D) + (!B | C) + (!B | D)\n");
+}
+
+#[test]
+fn explain() {
+    let input_file = test_utils::input_file_path("integration_test/consistent_input.txt");
+    run_cmd!(
+        "explain".to_string(),
+        input_file.display().to_string(),
+        "E".to_string()
+    )
+    .success()
+    .stdout(indoc! {"
+        E is true because C=>E
+          C is true because A|B=>C
+            A is true because it is a fact
+            B is true because it is a fact
+    "});
+}
+
+#[test]
+fn solve_explain() {
+    let input_file = test_utils::input_file_path("integration_test/spacing.txt");
+    run_cmd!("--explain".to_string(), input_file.display().to_string())
+        .success()
+        .stdout(indoc! {"
+            Z is true because A=>Z
+              A is true because it is a fact
+        "});
+}
+
+#[test]
+fn repl() {
+    let input_file = test_utils::input_file_path("integration_test/spacing.txt");
+    assert_cmd::Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .args(["repl".to_string(), input_file.display().to_string()])
+        .write_stdin("?Z\n:retract A\n?Z\n")
+        .assert()
+        .success()
+        .stdout("> Z is true\n> > Z is false\n> \n");
+}
+
+#[test]
+fn help() {
+    run_cmd!("--help").success().stdout(USAGE);
+}
+
+#[test]
+fn version() {
+    run_cmd!("--version").success().stdout(format!(
+        "{} {}\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    ));
+}
+
 #[test]
 fn error_usage_no_arguments() {
-    run_cmd!().failure().stderr(USAGE);
+    run_cmd!()
+        .failure()
+        .stderr(format!("Error: Missing command\n\n{}", USAGE));
 }
 
 #[test]
 fn error_usage_to_many_arguments() {
-    run_cmd!("foo", "bar").failure().stderr(USAGE);
+    run_cmd!("foo", "bar")
+        .failure()
+        .stderr(format!("Error: Unexpected argument: 'bar'\n\n{}", USAGE));
+}
+
+#[test]
+fn error_usage_unknown_option() {
+    run_cmd!("--foo", "bar")
+        .failure()
+        .stderr(format!("Error: Unknown option: '--foo'\n\n{}", USAGE));
+}
+
+#[test]
+fn error_usage_option_for_other_command() {
+    run_cmd!("--satisfying", "a.txt").failure().stderr(format!(
+        "Error: --satisfying is only available for table\n\n{}",
+        USAGE
+    ));
+}
+
+#[test]
+fn error_table_invalid_rule() {
+    run_cmd!("table", "A = B").failure();
 }
 
 #[test]
@@ -66,6 +598,26 @@ fn error_invalid_rule() {
     run_cmd!(input_file.display().to_string()).failure();
 }
 
+#[test]
+fn error_invalid_rule_location() {
+    let input_file = test_utils::input_file_path("integration_test/invalid_rule.txt");
+    let assert = run_cmd!(input_file.display().to_string()).failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert_eq!(
+        stderr,
+        format!(
+            indoc! {"
+                Error: Unable to finish lexing implicator
+                 --> {}:1:3
+                  |
+                1 | A = Z
+                  |   ^^^
+            "},
+            input_file.display()
+        )
+    );
+}
+
 #[test]
 fn error_invalid_facts() {
     let input_file = test_utils::input_file_path("integration_test/invalid_facts.txt");
@@ -77,3 +629,20 @@ fn error_invalid_queries() {
     let input_file = test_utils::input_file_path("integration_test/invalid_queries.txt");
     run_cmd!(input_file.display().to_string()).failure();
 }
+
+#[test]
+fn error_max_depth() {
+    let input_file = test_utils::input_file_path("integration_test/chain.txt");
+    let file = input_file.display().to_string();
+    run_cmd!("--max-depth=4".to_string(), file.clone()).success();
+    let assert = run_cmd!("--max-depth=3".to_string(), file).failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert!(stderr.contains("Resolving needs 4 nested identifiers, at most 3 are supported"));
+}
+
+#[test]
+fn error_max_variables() {
+    let assert = run_cmd!("--max-variables", "2", "table", "A + B => C").failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert!(stderr.contains("Rule has 3 identifiers, at most 2 are supported"));
+}